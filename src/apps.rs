@@ -0,0 +1,261 @@
+//! The applications that have entries in a table, or hold a permission.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::store::Store;
+use comfy_table::Cell;
+
+use crate::{Format, is_not_found, lookup_all, new_table, permissions_cell, print_json};
+
+#[derive(Serialize, Debug)]
+struct AppRow {
+    app: String,
+    count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<Vec<String>>,
+}
+
+pub async fn apps(
+    proxy: &Store<'_>,
+    table: &str,
+    by_name: bool,
+    with_resources: bool,
+    format: Format,
+) -> ExitCode {
+    let ids = match proxy.list(table).await {
+        Ok(ids) => ids,
+        // the table has never been created on this system
+        Err(e) if is_not_found(&e) => Vec::new(),
+        Err(e) => {
+            fail!(e, "failed to list permissions: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    let mut apps: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (id, result) in lookup_all(proxy, table, &ids).await {
+        match result {
+            Ok((permissions, _)) => {
+                for app in permissions.into_keys() {
+                    apps.entry(app).or_default().push(id.clone());
+                }
+            }
+            Err(e) if is_not_found(&e) => {}
+            Err(e) => {
+                fail!(e, "failed to lookup {id}: {e}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    if apps.is_empty() {
+        return code;
+    }
+
+    let mut rows: Vec<AppRow> = apps
+        .into_iter()
+        .map(|(app, resources)| AppRow {
+            app,
+            count: resources.len(),
+            resources: with_resources.then_some(resources),
+        })
+        .collect();
+    if !by_name {
+        // stable, so equal counts stay sorted by name
+        rows.sort_by_key(|row| Reverse(row.count));
+    }
+
+    if format == Format::Json {
+        print_json(&rows);
+        return code;
+    }
+
+    let mut table = new_table();
+    let mut header = vec!["AppID", "Resources"];
+    if with_resources {
+        header.push("Resource IDs");
+    }
+    table.set_header(header);
+    for row in &rows {
+        let mut cells = vec![row.app.clone(), row.count.to_string()];
+        cells.extend(row.resources.as_ref().map(|ids| ids.join("\n")));
+        table.add_row(cells);
+    }
+
+    println!("{table}");
+    code
+}
+
+#[derive(Serialize, Debug)]
+struct HolderRow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    table: Option<String>,
+    app: String,
+    resources: Vec<String>,
+}
+
+/// Report the applications holding `permission` in `tables`, or not holding
+/// it with `invert`, together with the resources concerned.
+pub async fn audit(
+    proxy: &Store<'_>,
+    tables: &[String],
+    with_table: bool,
+    permission: &str,
+    invert: bool,
+    format: Format,
+) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    let mut rows = Vec::new();
+    for table in tables {
+        let ids = match proxy.list(table).await {
+            Ok(ids) => ids,
+            // the table has never been created on this system
+            Err(e) if is_not_found(&e) => continue,
+            Err(e) => {
+                fail!(e, "failed to list {table}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        let mut holders: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (id, result) in lookup_all(proxy, table, &ids).await {
+            match result {
+                Ok((apps, _)) => {
+                    for (app, permissions) in apps {
+                        if permissions.iter().any(|p| p == permission) != invert {
+                            holders.entry(app).or_default().push(id.clone());
+                        }
+                    }
+                }
+                Err(e) if is_not_found(&e) => {}
+                Err(e) => {
+                    fail!(e, "failed to lookup {id} in {table}: {e}");
+                    code = ExitCode::FAILURE;
+                }
+            }
+        }
+
+        rows.extend(holders.into_iter().map(|(app, mut resources)| {
+            resources.sort();
+            HolderRow {
+                table: with_table.then(|| table.clone()),
+                app,
+                resources,
+            }
+        }));
+    }
+
+    if format == Format::Json {
+        print_json(&rows);
+        return code;
+    }
+
+    let mut table = new_table();
+    if with_table {
+        table.set_header(vec!["Table", "AppID", "Resource IDs"]);
+    } else {
+        table.set_header(vec!["AppID", "Resource IDs"]);
+    }
+    for row in rows {
+        let mut cells: Vec<String> = row.table.into_iter().collect();
+        cells.extend([row.app, row.resources.join("\n")]);
+        table.add_row(cells);
+    }
+
+    println!("{table}");
+    code
+}
+
+#[derive(Serialize, Debug)]
+struct ComparedRow {
+    table: String,
+    id: String,
+    /// The permissions of every compared application, `None` for no entry
+    permissions: BTreeMap<String, Option<Vec<String>>>,
+    differs: bool,
+}
+
+/// Print the entries of `apps` side by side on every resource any of them
+/// appears in, marking the resources where they differ.
+pub async fn compare(
+    proxy: &Store<'_>,
+    tables: &[String],
+    apps: &[String],
+    diff_only: bool,
+    format: Format,
+) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    let mut rows = Vec::new();
+    for table in tables {
+        let mut ids = match proxy.list(table).await {
+            Ok(ids) => ids,
+            // the table has never been created on this system
+            Err(e) if is_not_found(&e) => continue,
+            Err(e) => {
+                fail!(e, "failed to list {table}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        ids.sort();
+        for (id, result) in lookup_all(proxy, table, &ids).await {
+            let mut entries = match result {
+                Ok((entries, _)) => entries,
+                Err(e) if is_not_found(&e) => continue,
+                Err(e) => {
+                    fail!(e, "failed to lookup {id} in {table}: {e}");
+                    code = ExitCode::FAILURE;
+                    continue;
+                }
+            };
+
+            let permissions: BTreeMap<String, Option<Vec<String>>> = apps
+                .iter()
+                .map(|app| (app.clone(), entries.remove(app)))
+                .collect();
+            if permissions.values().all(Option::is_none) {
+                continue;
+            }
+            let mut values = permissions.values();
+            let first = values.next();
+            let differs = values.any(|value| Some(value) != first);
+            if diff_only && !differs {
+                continue;
+            }
+            rows.push(ComparedRow {
+                table: table.clone(),
+                id,
+                permissions,
+                differs,
+            });
+        }
+    }
+
+    if format == Format::Json {
+        print_json(&rows);
+        return code;
+    }
+
+    let mut table = new_table();
+    let mut header = vec!["Table", "Resource ID"];
+    header.extend(apps.iter().map(String::as_str));
+    header.push("");
+    table.set_header(header);
+    for row in rows {
+        let mut cells = vec![Cell::new(row.table), Cell::new(row.id)];
+        cells.extend(apps.iter().map(|app| match &row.permissions[app] {
+            Some(permissions) => permissions_cell(permissions),
+            None => Cell::new("-"),
+        }));
+        cells.push(Cell::new(if row.differs { "!" } else { "" }));
+        table.add_row(cells);
+    }
+
+    println!("{table}");
+    code
+}
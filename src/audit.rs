@@ -0,0 +1,132 @@
+//! Append-only JSON-lines log of the writes made to the permission store.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::SystemTime;
+
+use serde::Serialize;
+use zbus::zvariant::Value;
+
+#[derive(Serialize, Debug)]
+struct Record<'a> {
+    timestamp: String,
+    command: &'a str,
+    operation: &'a str,
+    table: &'a str,
+    id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    app: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    permissions: Option<&'a [String]>,
+    /// The permissions of every application, for writes of a whole resource
+    #[serde(skip_serializing_if = "Option::is_none")]
+    apps: Option<BTreeMap<&'a str, &'a [String]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<&'a Value<'a>>,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A single write to record, filled in by the store wrapper.
+pub struct Operation<'a> {
+    pub operation: &'a str,
+    pub table: &'a str,
+    pub id: &'a str,
+    pub app: Option<&'a str>,
+    pub permissions: Option<&'a [String]>,
+    pub apps: Option<&'a HashMap<String, Vec<String>>>,
+    pub data: Option<&'a Value<'a>>,
+}
+
+#[derive(Debug)]
+pub struct AuditLog {
+    path: String,
+}
+
+impl AuditLog {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    /// Append the outcome of `operation`; failing to log is reported but never fatal.
+    pub fn record(&self, command: &str, operation: &Operation<'_>, result: &zbus::Result<()>) {
+        let record = Record {
+            timestamp: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            command,
+            operation: operation.operation,
+            table: operation.table,
+            id: operation.id,
+            app: operation.app,
+            permissions: operation.permissions,
+            apps: operation.apps.map(|apps| {
+                apps.iter()
+                    .map(|(app, permissions)| (app.as_str(), permissions.as_slice()))
+                    .collect()
+            }),
+            data: operation.data,
+            status: if result.is_ok() { "ok" } else { "failed" },
+            error: result.as_ref().err().map(ToString::to_string),
+        };
+
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                fail!(e, "failed to serialize audit record: {e}");
+                return;
+            }
+        };
+
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{line}"));
+        if let Err(e) = result {
+            fail!(e, "failed to write audit log {}: {e}", self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_records_every_application_and_the_data() {
+        let path = std::env::temp_dir().join(format!("xdp-perm-audit-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = AuditLog::new(path.to_str().unwrap());
+        let apps = HashMap::from([
+            ("org.b.B".to_string(), vec!["no".to_string()]),
+            ("org.a.A".to_string(), vec!["yes".to_string()]),
+        ]);
+        let data = Value::from("exact");
+        let operation = Operation {
+            operation: "set",
+            table: "location",
+            id: "location",
+            app: None,
+            permissions: None,
+            apps: Some(&apps),
+            data: Some(&data),
+        };
+        log.record("move", &operation, &Ok(()));
+
+        let line = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            record["apps"],
+            serde_json::json!({"org.a.A": ["yes"], "org.b.B": ["no"]})
+        );
+        assert_eq!(
+            record["data"],
+            serde_json::json!({"signature": "s", "value": "exact"})
+        );
+        assert_eq!(record["status"], "ok");
+    }
+}
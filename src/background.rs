@@ -0,0 +1,109 @@
+//! Shortcuts for the background table, which only has a single resource.
+
+use std::process::ExitCode;
+
+use serde::Serialize;
+use zbus::zvariant::OwnedValue;
+
+use crate::error::ErrorKind;
+use crate::shortcut::{Resource, check_app};
+use crate::store::Store;
+use crate::{BackgroundAction, Format, data_is_unset, print_json, tables};
+
+const RESOURCE: Resource = Resource {
+    table: "background",
+    id: "background",
+    what: "background",
+};
+
+#[derive(Serialize, Debug)]
+struct Status<'a> {
+    app: &'a str,
+    /// `None` when the application has no entry
+    permissions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<OwnedValue>,
+}
+
+/// Whether `permissions` is one of the values the portal understands.
+fn is_known(permissions: &[String]) -> bool {
+    permissions.len() == 1 && tables::unknown_values(RESOURCE.table, permissions).is_empty()
+}
+
+pub async fn background(
+    proxy: &Store<'_>,
+    action: BackgroundAction,
+    app: Option<&str>,
+    format: Format,
+) -> ExitCode {
+    if action == BackgroundAction::List {
+        return RESOURCE.list(proxy, "Background", format).await;
+    }
+    let Some(app) = app else {
+        fail!(ErrorKind::InvalidInput, "expected <APP>");
+        return ExitCode::FAILURE;
+    };
+    if !check_app(app) {
+        return ExitCode::FAILURE;
+    }
+
+    let Some((mut apps, data)) = RESOURCE.entries(proxy).await else {
+        return ExitCode::FAILURE;
+    };
+    let current = apps.remove(app);
+
+    let permission = match action {
+        BackgroundAction::Allow => "yes",
+        BackgroundAction::Deny => "no",
+        BackgroundAction::Ask => "ask",
+        BackgroundAction::Status => return print_status(app, current, data, format),
+        BackgroundAction::List => unreachable!("handled above"),
+    };
+
+    if let Some(current) = current.as_deref().filter(|current| !is_known(current)) {
+        eprintln!(
+            "warning: replacing the unexpected background value {} of {app}",
+            current.join(",")
+        );
+    }
+    if !RESOURCE.set(proxy, app, &[permission.to_string()]).await {
+        return ExitCode::FAILURE;
+    }
+    status!("{app}: background permission set to {permission}");
+    ExitCode::SUCCESS
+}
+
+fn print_status(
+    app: &str,
+    permissions: Option<Vec<String>>,
+    data: Option<OwnedValue>,
+    format: Format,
+) -> ExitCode {
+    // the byte 0 the store returns without data tells nothing
+    let data = data.filter(|data| !data_is_unset(data));
+    if format == Format::Json {
+        print_json(&Status {
+            app,
+            permissions,
+            data,
+        });
+        return ExitCode::SUCCESS;
+    }
+
+    match permissions.as_deref() {
+        None => println!("{app} has no background setting, the portal will ask"),
+        Some([value]) if value == "yes" => println!("{app} may run in the background"),
+        Some([value]) if value == "no" => println!("{app} may not run in the background"),
+        Some([value]) if value == "ask" => {
+            println!("{app} is asked before running in the background")
+        }
+        Some(values) => println!(
+            "warning: {app} has the unexpected background value {}",
+            values.join(",")
+        ),
+    }
+    if let Some(data) = data {
+        println!("data: {}", &*data);
+    }
+    ExitCode::SUCCESS
+}
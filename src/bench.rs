@@ -0,0 +1,86 @@
+//! Measuring how fast the permission store answers, see `bench-lookup`.
+
+use std::process::ExitCode;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::store::Store;
+use crate::{Format, NO_HEADER, interrupt, new_table, print_json};
+
+/// Latencies of the calls in microseconds, so that JSON stays plain numbers.
+#[derive(Serialize, Debug)]
+struct Summary {
+    calls: usize,
+    min_us: u128,
+    median_us: u128,
+    p99_us: u128,
+    max_us: u128,
+}
+
+/// The nearest-rank percentile `p` of the sorted `latencies`.
+fn percentile(latencies: &[Duration], p: usize) -> Duration {
+    let rank = (latencies.len() * p).div_ceil(100).max(1);
+    latencies[rank - 1]
+}
+
+/// Look `id` up `iterations` times over the same connection, or until
+/// Ctrl-C, and print how long the calls took.
+pub async fn lookup(
+    proxy: &Store<'_>,
+    table: &str,
+    id: &str,
+    iterations: usize,
+    format: Format,
+) -> ExitCode {
+    let mut latencies = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        if interrupt::interrupted() {
+            break;
+        }
+        let start = Instant::now();
+        if let Err(e) = proxy.lookup(table, id).await {
+            fail!(e, "failed to lookup {id}: {e}");
+            return ExitCode::FAILURE;
+        }
+        latencies.push(start.elapsed());
+    }
+    if latencies.is_empty() {
+        return ExitCode::from(interrupt::EXIT_CODE);
+    }
+    latencies.sort();
+
+    let summary = Summary {
+        calls: latencies.len(),
+        min_us: latencies[0].as_micros(),
+        median_us: percentile(&latencies, 50).as_micros(),
+        p99_us: percentile(&latencies, 99).as_micros(),
+        max_us: latencies[latencies.len() - 1].as_micros(),
+    };
+    // a summary of the calls made so far, but still an interrupted run
+    let code = if interrupt::interrupted() {
+        ExitCode::from(interrupt::EXIT_CODE)
+    } else {
+        ExitCode::SUCCESS
+    };
+    if format == Format::Json {
+        print_json(&summary);
+        return code;
+    }
+
+    let mut table = new_table();
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        table.set_header(vec!["Calls", "Min", "Median", "P99", "Max"]);
+    }
+    let show = |micros: u128| format!("{:.1?}", Duration::from_micros(micros as u64));
+    table.add_row(vec![
+        summary.calls.to_string(),
+        show(summary.min_us),
+        show(summary.median_us),
+        show(summary.p99_us),
+        show(summary.max_us),
+    ]);
+    println!("{table}");
+    code
+}
@@ -0,0 +1,154 @@
+//! Before/after views of the entries a write touched, see `--show-diff`.
+
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+use clap::Args;
+use serde::Serialize;
+
+use crate::config;
+use crate::store::Store;
+use crate::{COLOR, Format, is_not_found, print_json};
+
+/// Diff options shared by every command that writes permissions.
+#[derive(Args, Debug)]
+pub struct ShowDiffArgs {
+    /// Print how the permissions changed, the default with show-diff in
+    /// the configuration file
+    #[arg(long, default_value_t = false, overrides_with = "no_diff")]
+    show_diff: bool,
+
+    /// Skip the extra reads needed by --show-diff
+    #[arg(long, default_value_t = false, overrides_with = "show_diff")]
+    no_diff: bool,
+}
+
+impl ShowDiffArgs {
+    pub fn enabled(&self) -> bool {
+        (self.show_diff || config::get().show_diff) && !self.no_diff
+    }
+}
+
+/// The permissions of one application before and after a write, `None`
+/// meaning it had no entry.
+#[derive(Serialize, Debug)]
+pub struct Change {
+    id: String,
+    app: String,
+    before: Option<Vec<String>>,
+    after: Option<Vec<String>>,
+}
+
+/// Collects the changes made by a command and prints them at the end.
+pub struct ChangeLog<'a> {
+    proxy: &'a Store<'a>,
+    table: &'a str,
+    enabled: bool,
+    changes: Vec<Change>,
+}
+
+impl<'a> ChangeLog<'a> {
+    pub fn new(proxy: &'a Store<'a>, table: &'a str, args: &ShowDiffArgs) -> Self {
+        Self {
+            proxy,
+            table,
+            enabled: args.enabled(),
+            changes: Vec::new(),
+        }
+    }
+
+    async fn entries(&self, id: &str) -> Option<HashMap<String, Vec<String>>> {
+        match self.proxy.lookup(self.table, id).await {
+            Ok((entries, _)) => Some(entries),
+            Err(e) if is_not_found(&e) => Some(HashMap::new()),
+            Err(e) => {
+                fail!(e, "{id}: failed to lookup permissions for the diff: {e}");
+                None
+            }
+        }
+    }
+
+    /// Read the entries of `id` ahead of a write, `None` when disabled.
+    pub async fn before(&self, id: &str) -> Option<HashMap<String, Vec<String>>> {
+        if !self.enabled {
+            return None;
+        }
+        self.entries(id).await
+    }
+
+    /// Read the entries of `id` back after a write and record how `apps`
+    /// changed, every application of the resource without `apps`.
+    pub async fn after(
+        &mut self,
+        id: &str,
+        apps: Option<&[&str]>,
+        before: Option<HashMap<String, Vec<String>>>,
+    ) {
+        let Some(mut before) = before else {
+            return;
+        };
+        let Some(mut after) = self.entries(id).await else {
+            return;
+        };
+
+        let apps: Vec<String> = match apps {
+            Some(apps) => apps.iter().map(|app| app.to_string()).collect(),
+            None => {
+                let mut apps: Vec<String> = before.keys().chain(after.keys()).cloned().collect();
+                apps.sort();
+                apps.dedup();
+                apps
+            }
+        };
+        for app in apps {
+            self.changes.push(Change {
+                id: id.to_string(),
+                before: before.remove(&app),
+                after: after.remove(&app),
+                app,
+            });
+        }
+    }
+
+    /// The changes recorded so far, `None` when disabled.
+    pub fn recorded(&self) -> Option<&[Change]> {
+        self.enabled.then_some(self.changes.as_slice())
+    }
+
+    pub fn print(&self, format: Format) {
+        if !self.enabled {
+            return;
+        }
+        if format == Format::Json {
+            print_json(&self.changes);
+            return;
+        }
+
+        let color = COLOR.load(Ordering::Relaxed);
+        let paint = |sign: char, code: &str, permission: &str| {
+            if color {
+                println!("  \x1b[{code}m{sign} {permission}\x1b[0m");
+            } else {
+                println!("  {sign} {permission}");
+            }
+        };
+        for change in &self.changes {
+            let before = change.before.as_deref().unwrap_or_default();
+            let after = change.after.as_deref().unwrap_or_default();
+            let marker = match (&change.before, &change.after) {
+                (None, Some(_)) => " (created)",
+                (Some(_), None) => " (removed)",
+                (None, None) => " (no entry)",
+                _ if before == after => " (unchanged)",
+                _ => "",
+            };
+            println!("{} {}:{marker}", change.id, change.app);
+            for permission in before.iter().filter(|p| !after.contains(p)) {
+                paint('-', "31", permission);
+            }
+            for permission in after.iter().filter(|p| !before.contains(p)) {
+                paint('+', "32", permission);
+            }
+        }
+    }
+}
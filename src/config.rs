@@ -0,0 +1,93 @@
+//! Defaults for command line options, read from
+//! `$XDG_CONFIG_HOME/xdg-perm/config.toml`:
+//!
+//! ```toml
+//! # --show-diff for set, revoke and delete, --no-diff still skips it
+//! show-diff = true
+//! # set --strict, --no-strict still only warns
+//! strict = true
+//! # set --if-changed, --always-write still writes
+//! if-changed = true
+//! ```
+//!
+//! A missing file is the same as an empty one.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// `--show-diff` unless `--no-diff` is given
+    pub show_diff: bool,
+    /// `set --strict` unless `--no-strict` is given
+    pub strict: bool,
+    /// `set --if-changed` unless `--always-write` is given
+    pub if_changed: bool,
+}
+
+/// The configuration file, `None` without `XDG_CONFIG_HOME` and `HOME`.
+fn path() -> Option<PathBuf> {
+    let non_empty = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+    let dir = non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("xdg-perm").join("config.toml"))
+}
+
+/// Read the configuration file for `get`. Returns whether it is usable,
+/// reporting the failure when it is not.
+pub fn load() -> bool {
+    let Some(path) = path() else {
+        return true;
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return true,
+        Err(e) => {
+            fail!(e, "failed to read {}: {e}", path.display());
+            return false;
+        }
+    };
+    match toml::from_str(&text) {
+        Ok(config) => {
+            let _ = CONFIG.set(config);
+            true
+        }
+        Err(e) => {
+            let e = format!("{}: {e}", path.display());
+            fail!(e, "{e}");
+            false
+        }
+    }
+}
+
+/// The loaded configuration, the defaults before `load`.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_kebab_case() {
+        let config: Config =
+            toml::from_str("show-diff = true\nstrict = true\nif-changed = true").unwrap();
+        assert!(config.show_diff && config.strict && config.if_changed);
+        let config: Config = toml::from_str("").unwrap();
+        assert!(!config.show_diff && !config.strict && !config.if_changed);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("show_diff = true").is_err());
+        assert!(toml::from_str::<Config>("if_changed = true").is_err());
+        assert!(toml::from_str::<Config>("show-diff = \"yes\"").is_err());
+    }
+}
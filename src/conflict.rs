@@ -0,0 +1,138 @@
+//! Noticing another client changing a resource between reading and writing
+//! it back, which read-modify-write commands then read again.
+
+use futures::{FutureExt, StreamExt};
+
+use crate::ChangedStream;
+use crate::store::Store;
+
+/// How many times a read-modify-write reads again when the resource changed
+/// before it could be written, before writing anyway.
+pub const RETRIES: usize = 3;
+
+/// Something telling whether a resource changed since it was last asked.
+pub trait Watch {
+    fn changed(&mut self) -> bool;
+}
+
+/// Read `value` again with `read` for as long as `watch` saw the resource
+/// change, at most `RETRIES` times. `None` once a read fails, which `read`
+/// reports.
+pub async fn reread<T>(
+    watch: &mut impl Watch,
+    mut value: T,
+    mut read: impl AsyncFnMut() -> Option<T>,
+) -> Option<T> {
+    let mut rereads = 0;
+    while rereads < RETRIES && watch.changed() {
+        rereads += 1;
+        value = read().await?;
+    }
+    Some(value)
+}
+
+/// The `Changed` signals for one resource since watching started.
+pub struct ConflictWatch {
+    signals: Option<ChangedStream>,
+}
+
+impl ConflictWatch {
+    /// Start watching `id` of `table`, which must happen before the read.
+    /// Nothing is watched when disabled or when subscribing fails, which is
+    /// only reported.
+    pub async fn start(proxy: &Store<'_>, table: &str, id: &str, enabled: bool) -> Self {
+        let signals = if enabled {
+            match proxy
+                .receive_changed_with_args(&[(0, table), (1, id)])
+                .await
+            {
+                Ok(signals) => Some(signals),
+                Err(e) => {
+                    eprintln!("warning: cannot watch {id} for concurrent changes: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Self { signals }
+    }
+}
+
+impl Watch for ConflictWatch {
+    /// Whether the resource changed since the last call. Only signals that
+    /// already arrived count, so a change made right before the write can
+    /// still slip through.
+    fn changed(&mut self) -> bool {
+        let Some(signals) = &mut self.signals else {
+            return false;
+        };
+        let mut changed = false;
+        while let Some(Some(_)) = signals.next().now_or_never() {
+            changed = true;
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+
+    /// Changed on every call while `changes` lasts.
+    struct Fake {
+        changes: usize,
+        asked: usize,
+    }
+
+    impl Watch for Fake {
+        fn changed(&mut self) -> bool {
+            self.asked += 1;
+            self.changes.checked_sub(1).is_some_and(|left| {
+                self.changes = left;
+                true
+            })
+        }
+    }
+
+    fn run(changes: usize, fail_at: Option<usize>) -> (Option<usize>, Fake) {
+        let mut watch = Fake { changes, asked: 0 };
+        let mut reads = 0;
+        let value = block_on(reread(&mut watch, 0, async || {
+            reads += 1;
+            (fail_at != Some(reads)).then_some(reads)
+        }));
+        (value, watch)
+    }
+
+    #[test]
+    fn unchanged_resources_are_not_read_again() {
+        let (value, watch) = run(0, None);
+        assert_eq!(value, Some(0));
+        assert_eq!(watch.asked, 1);
+    }
+
+    #[test]
+    fn changed_resources_are_read_again() {
+        let (value, watch) = run(2, None);
+        assert_eq!(value, Some(2));
+        // the third check saw no change and ended the loop
+        assert_eq!(watch.asked, 3);
+    }
+
+    #[test]
+    fn rereads_stop_after_the_retries() {
+        let (value, watch) = run(10, None);
+        assert_eq!(value, Some(RETRIES));
+        assert_eq!(watch.asked, RETRIES);
+    }
+
+    #[test]
+    fn a_failed_read_stops_the_rereads() {
+        let (value, watch) = run(10, Some(2));
+        assert_eq!(value, None);
+        assert_eq!(watch.asked, 2);
+    }
+}
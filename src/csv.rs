@@ -0,0 +1,284 @@
+//! A table as comma-separated values, one row per application entry, and
+//! back again.
+//!
+//! Every row holds the resource ID, the application ID and then one
+//! permission per column, so that any permission survives a spreadsheet.
+//! A resource without entries gets a row with an empty application.
+
+use std::collections::{BTreeMap, HashMap};
+use std::process::ExitCode;
+
+use zbus::zvariant::OwnedValue;
+
+use crate::store::Store;
+use crate::{is_not_found, lookup_all};
+
+const HEADER: [&str; 3] = ["resource", "app", "permissions"];
+
+/// Quote a CSV field if it holds a separator, a quote or a line break.
+pub fn field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Split `text` into records of fields, quoted ones included.
+fn parse(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                current.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if current.is_empty() => quoted = true,
+            '"' => return Err(format!("line {line}: a quote inside an unquoted field")),
+            ',' if !quoted => record.push(std::mem::take(&mut current)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut current));
+                records.push(std::mem::take(&mut record));
+                line += 1;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                current.push(c);
+            }
+        }
+    }
+    if quoted {
+        return Err(format!("line {line}: unterminated quoted field"));
+    }
+    if !current.is_empty() || !record.is_empty() {
+        record.push(current);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// The CSV text of `resources`, header included.
+fn write(resources: &BTreeMap<String, BTreeMap<String, Vec<String>>>) -> String {
+    let mut text = HEADER.join(",") + "\n";
+    for (id, apps) in resources {
+        if apps.is_empty() {
+            text += &format!("{},\n", field(id));
+        }
+        for (app, permissions) in apps {
+            let fields: Vec<String> = [id, app]
+                .into_iter()
+                .chain(permissions)
+                .map(|value| field(value))
+                .collect();
+            text += &(fields.join(",") + "\n");
+        }
+    }
+    text
+}
+
+/// The entries of every resource in the CSV `text`, which may start with
+/// the header.
+fn read(text: &str) -> Result<BTreeMap<String, HashMap<String, Vec<String>>>, String> {
+    let mut records = parse(text)?;
+    let is_header =
+        |record: &Vec<String>| record.iter().zip(HEADER).all(|(field, name)| field == name);
+    if records.first().is_some_and(is_header) {
+        records.remove(0);
+    }
+
+    // BTreeMap, so that resources are written in a stable order
+    let mut resources: BTreeMap<String, HashMap<String, Vec<String>>> = BTreeMap::new();
+    for (i, record) in records.into_iter().enumerate() {
+        let mut fields = record.into_iter();
+        let (Some(id), app) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if id.is_empty() {
+            return Err(format!("record {} has no resource ID", i + 1));
+        }
+        let apps = resources.entry(id).or_default();
+        // no application makes a resource without entries
+        if let Some(app) = app.filter(|app| !app.is_empty()) {
+            apps.entry(app).or_default().extend(fields);
+        }
+    }
+    Ok(resources)
+}
+
+/// Write every entry of `table` to `path`, or to stdout for `-`.
+pub async fn export(proxy: &Store<'_>, table: &str, path: &str) -> ExitCode {
+    let ids = match proxy.list(table).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            fail!(e, "failed to list permissions: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    let mut resources = BTreeMap::new();
+    for (id, result) in lookup_all(proxy, table, &ids).await {
+        match result {
+            Ok((apps, _)) => {
+                resources.insert(id, apps.into_iter().collect::<BTreeMap<_, _>>());
+            }
+            // the resource was deleted while exporting
+            Err(e) if is_not_found(&e) => {}
+            Err(e) => {
+                fail!(e, "failed to lookup {id}: {e}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let text = write(&resources);
+    if path == "-" {
+        print!("{text}");
+        return code;
+    }
+    if let Err(e) = std::fs::write(path, text) {
+        fail!(e, "failed to write {path}: {e}");
+        return ExitCode::FAILURE;
+    }
+    let rows: usize = resources.values().map(BTreeMap::len).sum();
+    status!(
+        "{rows} entries of {} resource(s) written to {path}",
+        resources.len()
+    );
+    code
+}
+
+/// Replace the entries of every resource in the CSV at `path`, or stdin for
+/// `-`, keeping the associated data. Resources left out stay as they are.
+pub async fn import(proxy: &Store<'_>, table: &str, path: &str, create: bool) -> ExitCode {
+    let text = if path == "-" {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(path)
+    };
+    let text = match text {
+        Ok(text) => text,
+        Err(e) => {
+            fail!(e, "failed to read {path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let resources = match read(&text) {
+        Ok(resources) => resources,
+        Err(e) => {
+            fail!(e, "{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    let mut imported = 0;
+    // resources that failed to be looked up never wrote, so never created the table
+    let mut attempted = false;
+    for (id, apps) in &resources {
+        let data = match proxy.lookup(table, id).await {
+            Ok((_, data)) => data,
+            // new resources get no data, like the store gives them
+            Err(e) if is_not_found(&e) => OwnedValue::from(0u8),
+            Err(e) => {
+                fail!(e, "failed to lookup {id}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        // only the first write needs to create the table
+        let first = create && !attempted;
+        attempted = true;
+        let mut result = proxy.set(table, first, id, apps, &data).await;
+        if !first && create && result.as_ref().is_err_and(is_not_found) {
+            // another client may have deleted the table since, retry once
+            result = proxy.set(table, true, id, apps, &data).await;
+        }
+        match result {
+            Ok(_) => imported += 1,
+            Err(e) => {
+                fail!(e, "{id}: failed to import: {e}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    status!("Imported {imported} resource(s) into {table} from {path}");
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(apps: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        apps.iter()
+            .map(|(app, permissions)| {
+                let permissions = permissions.iter().map(ToString::to_string).collect();
+                (app.to_string(), permissions)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let resources = BTreeMap::from([
+            (
+                "plain".to_string(),
+                entries(&[("org.a.A", &["yes", "ask"]), ("org.b.B", &["no"])]),
+            ),
+            (
+                "with, comma".to_string(),
+                entries(&[("org.a.A", &["a,b", "say \"hi\"", "two\nlines", "cr\r\nlf"])]),
+            ),
+            (
+                "\"quoted\"".to_string(),
+                entries(&[
+                    ("org.a.A", &[]),
+                    ("org.b.B", &[""]),
+                    ("org.c.C", &["", "x"]),
+                ]),
+            ),
+            ("no entries".to_string(), BTreeMap::new()),
+        ]);
+
+        let text = write(&resources);
+        let read: BTreeMap<String, BTreeMap<String, Vec<String>>> = read(&text)
+            .unwrap()
+            .into_iter()
+            .map(|(id, apps)| (id, apps.into_iter().collect()))
+            .collect();
+        assert_eq!(read, resources, "{text}");
+    }
+
+    #[test]
+    fn fields_are_quoted_only_when_needed() {
+        assert_eq!(field("yes"), "yes");
+        assert_eq!(field(""), "");
+        assert_eq!(field("a,b"), "\"a,b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn header_is_optional() {
+        let resources = read("r,org.a.A,yes\nr,org.b.B\n").unwrap();
+        assert_eq!(resources["r"]["org.a.A"], ["yes"]);
+        assert!(resources["r"]["org.b.B"].is_empty());
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        assert!(read("r,\"unterminated\n").is_err());
+        assert!(read("r,a\"b\n").is_err());
+        assert!(read(",org.a.A,yes\n").is_err());
+    }
+}
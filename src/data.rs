@@ -0,0 +1,248 @@
+//! Associated data given as JSON, see `set --data-file`.
+
+use std::collections::HashMap;
+
+use zbus::zvariant::{Array, Dict, ObjectPath, OwnedValue, Signature, StructureBuilder, Value};
+
+/// Read the data in the JSON file at `path`, `None` once the failure is
+/// reported.
+pub fn read(path: &str) -> Option<OwnedValue> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            fail!(e, "failed to read {path}: {e}");
+            return None;
+        }
+    };
+    let json = match serde_json::from_str(&text) {
+        Ok(json) => json,
+        Err(e) => {
+            fail!(e, "{path} is not valid JSON: {e}");
+            return None;
+        }
+    };
+    match from_json(json) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            fail!(e, "{path}: {e}");
+            None
+        }
+    }
+}
+
+/// Convert `json` the way `lookup --format json` prints data, an object with
+/// the `signature` and the `value`, or else as plain JSON: objects become
+/// `a{sv}`, arrays `av`, numbers `x` or `d`.
+pub fn from_json(json: serde_json::Value) -> Result<OwnedValue, String> {
+    let value = match json {
+        serde_json::Value::Object(object) if is_signed(&object) => signed(object)?,
+        json => to_value(json)?,
+    };
+    OwnedValue::try_from(value).map_err(|e| format!("invalid data: {e}"))
+}
+
+/// Whether `object` is a value along with its signature.
+fn is_signed(object: &serde_json::Map<String, serde_json::Value>) -> bool {
+    object.len() == 2 && object.contains_key("signature") && object.contains_key("value")
+}
+
+/// The value of an object with the `signature` and the `value`.
+fn signed(
+    mut object: serde_json::Map<String, serde_json::Value>,
+) -> Result<Value<'static>, String> {
+    let signature = match object.remove("signature") {
+        Some(serde_json::Value::String(signature)) => Signature::try_from(signature.as_str())
+            .map_err(|e| format!("invalid signature {signature}: {e}"))?,
+        _ => return Err("the signature is not a string".to_string()),
+    };
+    typed(&signature, object.remove("value").unwrap_or_default())
+}
+
+/// An integer of the type `T`, if `number` is one in its range.
+fn integer<T: TryFrom<i128>>(number: &serde_json::Number) -> Option<T> {
+    let number = number
+        .as_i64()
+        .map(i128::from)
+        .or_else(|| number.as_u64().map(i128::from))?;
+    T::try_from(number).ok()
+}
+
+/// Convert `json` to a value of the type `signature`.
+fn typed(signature: &Signature, json: serde_json::Value) -> Result<Value<'static>, String> {
+    use serde_json::Value as Json;
+
+    let mismatch = |json: &Json| format!("expected {signature}, got {json}");
+    let value = match (signature, json) {
+        (Signature::Bool, Json::Bool(value)) => Some(value.into()),
+        (Signature::U8, Json::Number(number)) => integer::<u8>(&number).map(Value::from),
+        (Signature::I16, Json::Number(number)) => integer::<i16>(&number).map(Value::from),
+        (Signature::U16, Json::Number(number)) => integer::<u16>(&number).map(Value::from),
+        (Signature::I32, Json::Number(number)) => integer::<i32>(&number).map(Value::from),
+        (Signature::U32, Json::Number(number)) => integer::<u32>(&number).map(Value::from),
+        (Signature::I64, Json::Number(number)) => integer::<i64>(&number).map(Value::from),
+        (Signature::U64, Json::Number(number)) => integer::<u64>(&number).map(Value::from),
+        (Signature::F64, Json::Number(number)) => number.as_f64().map(Value::from),
+        (Signature::Str, Json::String(value)) => Some(value.into()),
+        (Signature::ObjectPath, Json::String(value)) => {
+            ObjectPath::try_from(value).ok().map(Value::from)
+        }
+        (Signature::Signature, Json::String(value)) => {
+            Signature::try_from(value.as_str()).ok().map(Value::from)
+        }
+        (Signature::Variant, Json::Object(object)) if is_signed(&object) => {
+            Some(Value::Value(Box::new(signed(object)?)))
+        }
+        (Signature::Array(child), Json::Array(values)) => {
+            let mut array = Array::new(child);
+            for value in values {
+                array
+                    .append(typed(child, value)?)
+                    .map_err(|e| e.to_string())?;
+            }
+            Some(array.into())
+        }
+        (Signature::Dict { key, value }, Json::Object(object)) => {
+            let mut dict = Dict::new(key, value);
+            for (name, entry) in object {
+                // JSON keys are strings, whatever the key type
+                let name = match &**key {
+                    Signature::Str | Signature::ObjectPath | Signature::Signature => {
+                        Json::String(name)
+                    }
+                    _ => serde_json::from_str(&name)
+                        .map_err(|_| format!("expected {} keys, got {name}", &**key))?,
+                };
+                let entry = typed(value, entry).map_err(|e| format!("{name}: {e}"))?;
+                dict.append(typed(key, name)?, entry)
+                    .map_err(|e| e.to_string())?;
+            }
+            Some(dict.into())
+        }
+        (Signature::Structure(fields), Json::Array(values)) if fields.len() == values.len() => {
+            let mut structure = StructureBuilder::new();
+            for (field, value) in fields.iter().zip(values) {
+                structure = structure.append_field(typed(field, value)?);
+            }
+            Some(structure.build().map_err(|e| e.to_string())?.into())
+        }
+        (_, json) => return Err(mismatch(&json)),
+    };
+    value.ok_or_else(|| format!("{signature} out of range or invalid"))
+}
+
+fn to_value(json: serde_json::Value) -> Result<Value<'static>, String> {
+    Ok(match json {
+        serde_json::Value::Null => return Err("null has no D-Bus type".to_string()),
+        serde_json::Value::Bool(value) => value.into(),
+        serde_json::Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+            (Some(value), _) => value.into(),
+            (None, Some(value)) => value.into(),
+            (None, None) => number.as_f64().unwrap_or_default().into(),
+        },
+        serde_json::Value::String(value) => value.into(),
+        serde_json::Value::Array(values) => {
+            let values: Vec<Value<'static>> = values
+                .into_iter()
+                .map(|value| to_value(value).map(|value| Value::Value(Box::new(value))))
+                .collect::<Result<_, _>>()?;
+            values.into()
+        }
+        serde_json::Value::Object(object) => {
+            let entries: HashMap<String, Value<'static>> = object
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = to_value(value).map_err(|e| format!("{key}: {e}"))?;
+                    Ok((key, Value::Value(Box::new(value))))
+                })
+                .collect::<Result<_, String>>()?;
+            entries.into()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Print `value` the way `lookup --format json` does and read it back.
+    fn round_trip(value: Value<'static>) {
+        let owned = OwnedValue::try_from(value).unwrap();
+        let json = serde_json::to_value(&owned).unwrap();
+        let back = from_json(json.clone()).unwrap_or_else(|e| panic!("{json}: {e}"));
+        assert_eq!(back, owned, "{json}");
+        assert_eq!(serde_json::to_value(&back).unwrap(), json);
+    }
+
+    #[test]
+    fn basic_types_round_trip() {
+        round_trip(true.into());
+        round_trip(u8::MAX.into());
+        round_trip(i16::MIN.into());
+        round_trip(u16::MAX.into());
+        round_trip(i32::MIN.into());
+        round_trip(u32::MAX.into());
+        round_trip(i64::MIN.into());
+        round_trip(u64::MAX.into());
+        round_trip(1.5f64.into());
+        round_trip("exact \"city\"\n".into());
+        round_trip(
+            ObjectPath::try_from("/org/freedesktop/portal")
+                .unwrap()
+                .into(),
+        );
+        round_trip(Signature::try_from("a{sv}").unwrap().into());
+    }
+
+    #[test]
+    fn containers_round_trip() {
+        round_trip(vec!["a", "b"].into());
+        round_trip(Array::new(&Signature::U32).into());
+        round_trip(HashMap::from([("accuracy", 2u32), ("count", 0)]).into());
+        round_trip(HashMap::from([(1u32, "one"), (2, "two")]).into());
+        round_trip(
+            StructureBuilder::new()
+                .add_field(1u8)
+                .add_field("last")
+                .build()
+                .unwrap()
+                .into(),
+        );
+    }
+
+    #[test]
+    fn nested_variants_round_trip() {
+        let inner = HashMap::from([("accuracy", Value::from(2u32))]);
+        let outer = HashMap::from([
+            ("location", Value::Value(Box::new(inner.into()))),
+            ("timestamps", Value::from(vec![1u64, 2])),
+        ]);
+        round_trip(Value::Value(Box::new(Value::from(outer))));
+    }
+
+    #[test]
+    fn plain_json_is_typed_by_its_shape() {
+        let json = serde_json::json!({"name": "Maps", "count": 3, "tags": ["a"]});
+        let value = from_json(json).unwrap();
+        assert_eq!(value.value_signature().to_string(), "a{sv}");
+
+        assert_eq!(
+            from_json(serde_json::json!(-1)).unwrap(),
+            OwnedValue::from(-1i64)
+        );
+        assert_eq!(
+            from_json(serde_json::json!(0.5)).unwrap(),
+            OwnedValue::from(0.5f64)
+        );
+        assert!(from_json(serde_json::Value::Null).is_err());
+    }
+
+    #[test]
+    fn signed_values_are_checked() {
+        let out_of_range = serde_json::json!({"signature": "y", "value": 256});
+        assert!(from_json(out_of_range).is_err());
+        let mismatch = serde_json::json!({"signature": "s", "value": 1});
+        assert!(from_json(mismatch).is_err());
+        let bad_signature = serde_json::json!({"signature": "a{", "value": {}});
+        assert!(from_json(bad_signature).is_err());
+    }
+}
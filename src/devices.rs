@@ -0,0 +1,148 @@
+//! Shortcuts for the devices table and its fixed resources.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::process::ExitCode;
+use std::sync::atomic::Ordering;
+
+use comfy_table::Cell;
+
+use crate::error::ErrorKind;
+use crate::shortcut::{Resource, check_app};
+use crate::store::Store;
+use crate::{
+    Device, DeviceAction, Format, NO_HEADER, cell_text, new_table, permissions_cell, print_json,
+};
+
+const CAMERA: Resource = device("camera");
+const MICROPHONE: Resource = device("microphone");
+const SPEAKERS: Resource = device("speakers");
+
+const fn device(id: &'static str) -> Resource {
+    Resource {
+        table: "devices",
+        id,
+        what: id,
+    }
+}
+
+impl Device {
+    /// The resources the device stands for, all three for `all`.
+    fn resources(self) -> &'static [Resource] {
+        match self {
+            Device::Camera => &[CAMERA],
+            Device::Microphone => &[MICROPHONE],
+            Device::Speakers => &[SPEAKERS],
+            Device::All => &[CAMERA, MICROPHONE, SPEAKERS],
+        }
+    }
+}
+
+pub async fn devices(
+    proxy: &Store<'_>,
+    action: DeviceAction,
+    device: Device,
+    app: Option<&str>,
+    format: Format,
+) -> ExitCode {
+    if app.is_some_and(|app| !check_app(app)) {
+        return ExitCode::FAILURE;
+    }
+
+    let permission = match action {
+        DeviceAction::Allow => "yes",
+        DeviceAction::Deny => "no",
+        DeviceAction::Ask => "ask",
+        DeviceAction::Status => return print_status(proxy, device, app, format).await,
+    };
+    let Some(app) = app else {
+        fail!(ErrorKind::InvalidInput, "expected <APP>");
+        return ExitCode::FAILURE;
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    for resource in device.resources() {
+        if resource.set(proxy, app, &[permission.to_string()]).await {
+            status!("{app}: {} permission set to {permission}", resource.id);
+        } else {
+            code = ExitCode::FAILURE;
+        }
+    }
+    code
+}
+
+/// Print the permissions of `app`, or of every application, for each
+/// resource of `device`.
+async fn print_status(
+    proxy: &Store<'_>,
+    device: Device,
+    app: Option<&str>,
+    format: Format,
+) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    let mut resources: BTreeMap<&str, HashMap<String, Vec<String>>> = BTreeMap::new();
+    for resource in device.resources() {
+        match resource.entries(proxy).await {
+            Some((apps, _)) => {
+                resources.insert(resource.id, apps);
+            }
+            None => code = ExitCode::FAILURE,
+        }
+    }
+
+    let apps: BTreeSet<&str> = match app {
+        Some(app) => BTreeSet::from([app]),
+        None => resources
+            .values()
+            .flat_map(|apps| apps.keys().map(String::as_str))
+            .collect(),
+    };
+
+    if format == Format::Json {
+        // device to application to permissions, `null` for no entry
+        let json: BTreeMap<&str, BTreeMap<&str, Option<&Vec<String>>>> = resources
+            .iter()
+            .map(|(id, entries)| {
+                let entries = apps.iter().map(|app| (*app, entries.get(*app))).collect();
+                (*id, entries)
+            })
+            .collect();
+        match app {
+            Some(app) => {
+                let json: BTreeMap<&str, _> = json
+                    .iter()
+                    .map(|(id, entries)| (*id, entries[app]))
+                    .collect();
+                print_json(&json);
+            }
+            None => print_json(&json),
+        }
+        return code;
+    }
+
+    if apps.is_empty() {
+        let ids: Vec<&str> = device
+            .resources()
+            .iter()
+            .map(|resource| resource.id)
+            .collect();
+        status!("no application has a {} setting", ids.join(" or "));
+        return code;
+    }
+    let mut table = new_table();
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        let mut header = vec!["AppID"];
+        header.extend(resources.keys());
+        table.set_header(header);
+    }
+    for app in &apps {
+        let mut row = vec![Cell::new(cell_text(app))];
+        row.extend(resources.values().map(|entries| {
+            let permissions = entries.get(*app).map(Vec::as_slice).unwrap_or_default();
+            permissions_cell(permissions)
+        }));
+        table.add_row(row);
+    }
+
+    println!("{table}");
+    code
+}
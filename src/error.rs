@@ -0,0 +1,143 @@
+//! Failure reports scripts can rely on, see `fail!` and `--format json`.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+/// Set from `--format json` for the duration of a command.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Report failures as JSON objects from now on, or as plain text again.
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+/// Whether `--format json` is in effect, so stdout only carries the document.
+pub fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// What went wrong, stable so that scripts can branch on it.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The table or resource does not exist
+    NotFound,
+    /// Nothing provides the permission store on the session bus
+    ServiceUnknown,
+    /// The bus or the permission store refused the call
+    AccessDenied,
+    /// The permission store rejected the arguments of the call
+    InvalidArgs,
+    /// Any other D-Bus failure
+    Dbus,
+    /// Reading or writing a local file failed
+    Io,
+    /// A pattern, mapping or other input given on the command line is invalid
+    InvalidInput,
+    /// The target already has an entry, which only a flag lets us replace
+    AlreadyExists,
+    /// Reading a write back returned something else
+    Mismatch,
+    /// The user did not confirm a destructive operation
+    Aborted,
+}
+
+/// Errors that know their [`ErrorKind`].
+pub trait Classify {
+    fn kind(&self) -> ErrorKind;
+}
+
+impl<T: Classify + ?Sized> Classify for &T {
+    fn kind(&self) -> ErrorKind {
+        (**self).kind()
+    }
+}
+
+/// For the failures we detect ourselves rather than get from a call.
+impl Classify for ErrorKind {
+    fn kind(&self) -> ErrorKind {
+        *self
+    }
+}
+
+impl Classify for zbus::Error {
+    fn kind(&self) -> ErrorKind {
+        if crate::is_not_found(self) {
+            return ErrorKind::NotFound;
+        }
+        if crate::is_service_unknown(self) {
+            return ErrorKind::ServiceUnknown;
+        }
+        match self {
+            zbus::Error::MethodError(name, _, _) => match name.as_str() {
+                "org.freedesktop.DBus.Error.AccessDenied"
+                | "org.freedesktop.portal.Error.NotAllowed" => ErrorKind::AccessDenied,
+                "org.freedesktop.DBus.Error.InvalidArgs"
+                | "org.freedesktop.portal.Error.InvalidArgument" => ErrorKind::InvalidArgs,
+                _ => ErrorKind::Dbus,
+            },
+            zbus::Error::FDO(e) => match **e {
+                zbus::fdo::Error::AccessDenied(_) => ErrorKind::AccessDenied,
+                zbus::fdo::Error::InvalidArgs(_) => ErrorKind::InvalidArgs,
+                _ => ErrorKind::Dbus,
+            },
+            zbus::Error::InputOutput(_) => ErrorKind::Io,
+            _ => ErrorKind::Dbus,
+        }
+    }
+}
+
+impl Classify for std::io::Error {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Io
+    }
+}
+
+impl Classify for serde_json::Error {
+    fn kind(&self) -> ErrorKind {
+        if self.is_io() {
+            ErrorKind::Io
+        } else {
+            ErrorKind::InvalidInput
+        }
+    }
+}
+
+/// The messages the helpers build up themselves, mostly about bad input.
+impl Classify for String {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::InvalidInput
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct Report {
+    error: Detail,
+}
+
+#[derive(Serialize, Debug)]
+struct Detail {
+    kind: ErrorKind,
+    message: String,
+}
+
+/// Print a failure to stderr, as `{"error": {"kind", "message"}}` on a
+/// single line with `--format json`.
+pub fn report(kind: ErrorKind, message: fmt::Arguments<'_>) {
+    if !JSON.load(Ordering::Relaxed) {
+        eprintln!("{message}");
+        return;
+    }
+    let report = Report {
+        error: Detail {
+            kind,
+            message: message.to_string(),
+        },
+    };
+    match serde_json::to_string(&report) {
+        Ok(json) => eprintln!("{json}"),
+        Err(_) => eprintln!("{message}"),
+    }
+}
@@ -0,0 +1,242 @@
+//! Matching of resource IDs, application IDs and permissions on the client side.
+
+use clap::Args;
+use glob::{MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+
+/// Matching options shared by every command that filters its results.
+#[derive(Args, Debug)]
+pub struct MatchArgs {
+    /// Ignore case when matching
+    #[arg(short, long, default_value_t = false)]
+    pub ignore_case: bool,
+}
+
+impl MatchArgs {
+    /// Compare `value` against an exact `expected` string.
+    pub fn matches(&self, expected: &str, value: &str) -> bool {
+        if self.ignore_case {
+            expected.to_lowercase() == value.to_lowercase()
+        } else {
+            expected == value
+        }
+    }
+
+    /// Compile a shell-style glob, `*` and `?` match any characters.
+    pub fn glob(&self, pattern: &str) -> Result<Glob, String> {
+        let pattern = Pattern::new(pattern).map_err(|e| format!("invalid glob {pattern}: {e}"))?;
+        Ok(Glob {
+            pattern,
+            options: MatchOptions {
+                case_sensitive: !self.ignore_case,
+                ..MatchOptions::new()
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Glob {
+    pattern: Pattern,
+    options: MatchOptions,
+}
+
+impl Glob {
+    pub fn matches(&self, value: &str) -> bool {
+        self.pattern.matches_with(value, self.options)
+    }
+}
+
+/// Client-side filtering of the resource IDs a table holds.
+#[derive(Args, Debug)]
+pub struct IdFilterArgs {
+    /// Only keep the resource IDs matching this glob, which has to match the whole ID
+    #[arg(long, value_name = "GLOB")]
+    pub filter: Option<String>,
+
+    /// Only keep the resource IDs containing a match of this regular expression, use ^ and $ to anchor it
+    #[arg(long, value_name = "PATTERN")]
+    pub regex: Option<String>,
+
+    /// Drop the matching resource IDs instead of keeping them
+    #[arg(long, default_value_t = false)]
+    pub invert: bool,
+}
+
+impl IdFilterArgs {
+    pub fn compile(&self, matching: &MatchArgs) -> Result<IdFilter, String> {
+        let glob = self
+            .filter
+            .as_deref()
+            .map(|pattern| matching.glob(pattern))
+            .transpose()?;
+        let regex = self
+            .regex
+            .as_deref()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(matching.ignore_case)
+                    .build()
+                    .map_err(|e| format!("invalid regex {pattern}: {e}"))
+            })
+            .transpose()?;
+        Ok(IdFilter {
+            glob,
+            regex,
+            invert: self.invert,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct IdFilter {
+    glob: Option<Glob>,
+    regex: Option<Regex>,
+    invert: bool,
+}
+
+impl IdFilter {
+    fn is_active(&self) -> bool {
+        self.glob.is_some() || self.regex.is_some()
+    }
+
+    /// Whether `id` passes both the glob and the regex, or neither with `invert`.
+    pub fn matches(&self, id: &str) -> bool {
+        let glob = self.glob.as_ref().is_none_or(|glob| glob.matches(id));
+        let regex = self.regex.as_ref().is_none_or(|regex| regex.is_match(id));
+        (glob && regex) != self.invert
+    }
+
+    /// Keep the matching `ids`, reporting how many matched on stderr so a
+    /// mistyped pattern does not go unnoticed.
+    pub fn retain(&self, ids: &mut Vec<String>) {
+        if !self.is_active() {
+            return;
+        }
+        let total = ids.len();
+        ids.retain(|id| self.matches(id));
+        if !crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!("{} of {total} ids matched", ids.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(
+        glob: Option<&str>,
+        regex: Option<&str>,
+        invert: bool,
+        ignore_case: bool,
+    ) -> IdFilter {
+        let args = IdFilterArgs {
+            filter: glob.map(ToString::to_string),
+            regex: regex.map(ToString::to_string),
+            invert,
+        };
+        args.compile(&MatchArgs { ignore_case }).unwrap()
+    }
+
+    fn kept(filter: &IdFilter, ids: &[&str]) -> Vec<String> {
+        let mut ids = ids.iter().map(ToString::to_string).collect();
+        filter.retain(&mut ids);
+        ids
+    }
+
+    const IDS: &[&str] = &["camera", "my-camera", "Camera2", "a.b", "axb", "a*b"];
+
+    #[test]
+    fn globs_match_the_whole_id() {
+        assert_eq!(
+            kept(&filter(Some("cam*"), None, false, false), IDS),
+            ["camera"]
+        );
+        assert_eq!(
+            kept(&filter(Some("*cam*"), None, false, false), IDS),
+            ["camera", "my-camera"]
+        );
+        assert_eq!(
+            kept(&filter(Some("cam*"), None, false, true), IDS),
+            ["camera", "Camera2"]
+        );
+    }
+
+    #[test]
+    fn regexes_match_anywhere_unless_anchored() {
+        assert_eq!(
+            kept(&filter(None, Some("cam"), false, false), IDS),
+            ["camera", "my-camera"]
+        );
+        assert_eq!(
+            kept(&filter(None, Some("^cam"), false, false), IDS),
+            ["camera"]
+        );
+        assert_eq!(
+            kept(&filter(None, Some("^camera$"), false, true), IDS),
+            ["camera"]
+        );
+        assert_eq!(
+            kept(&filter(None, Some("2$"), false, false), IDS),
+            ["Camera2"]
+        );
+    }
+
+    #[test]
+    fn special_characters() {
+        // a dot is literal in a glob and any character in a regex
+        assert_eq!(kept(&filter(Some("a.b"), None, false, false), IDS), ["a.b"]);
+        assert_eq!(
+            kept(&filter(None, Some("^a.b$"), false, false), IDS),
+            ["a.b", "axb", "a*b"]
+        );
+        assert_eq!(
+            kept(&filter(None, Some(r"^a\.b$"), false, false), IDS),
+            ["a.b"]
+        );
+        // brackets quote the glob wildcards
+        assert_eq!(
+            kept(&filter(Some("a[*]b"), None, false, false), IDS),
+            ["a*b"]
+        );
+        assert_eq!(
+            kept(&filter(Some("a?b"), None, false, false), IDS),
+            ["a.b", "axb", "a*b"]
+        );
+    }
+
+    #[test]
+    fn invert_drops_the_matches() {
+        assert_eq!(
+            kept(&filter(Some("*cam*"), None, true, false), IDS),
+            ["Camera2", "a.b", "axb", "a*b"]
+        );
+        assert_eq!(
+            kept(&filter(None, Some(r"^a\.b$|^a\*b$"), true, false), IDS),
+            ["camera", "my-camera", "Camera2", "axb"]
+        );
+        // both have to match for an ID to be dropped
+        assert_eq!(
+            kept(&filter(Some("*camera"), Some("^my"), true, false), IDS),
+            ["camera", "Camera2", "a.b", "axb", "a*b"]
+        );
+    }
+
+    #[test]
+    fn no_pattern_keeps_everything() {
+        assert_eq!(kept(&filter(None, None, true, false), IDS), IDS);
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let matching = MatchArgs { ignore_case: false };
+        let args = |glob: Option<&str>, regex: Option<&str>| IdFilterArgs {
+            filter: glob.map(ToString::to_string),
+            regex: regex.map(ToString::to_string),
+            invert: false,
+        };
+        assert!(args(Some("[a"), None).compile(&matching).is_err());
+        assert!(args(None, Some("(a")).compile(&matching).is_err());
+    }
+}
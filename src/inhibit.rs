@@ -0,0 +1,204 @@
+//! Shortcuts for the inhibit table, which only has a single resource.
+//!
+//! The permissions of an application are the things it may inhibit without
+//! asking. The portal lets an application without an entry inhibit all of
+//! them, and one with an empty entry none.
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+use std::sync::atomic::Ordering;
+
+use comfy_table::{Cell, Color};
+use serde::Serialize;
+
+use crate::error::ErrorKind;
+use crate::shortcut::{Resource, check_app};
+use crate::store::Store;
+use crate::{
+    Format, InhibitAction, InhibitFlag, NO_HEADER, cell_text, new_table, print_json, tables,
+};
+
+const RESOURCE: Resource = Resource {
+    table: "inhibit",
+    id: "inhibit",
+    what: "inhibit",
+};
+
+/// The flags the portal understands, in the order it documents them.
+const FLAGS: [&str; 4] = ["logout", "switch", "suspend", "idle"];
+
+impl InhibitFlag {
+    fn stored(self) -> &'static str {
+        match self {
+            InhibitFlag::Logout => "logout",
+            InhibitFlag::Switch => "switch",
+            InhibitFlag::Suspend => "suspend",
+            InhibitFlag::Idle => "idle",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct Status<'a> {
+    app: &'a str,
+    /// `None` when the application has no entry
+    permissions: Option<Vec<String>>,
+    /// What the portal lets the application inhibit
+    allowed: Vec<&'static str>,
+}
+
+#[derive(Serialize, Debug)]
+struct Entry<'a> {
+    permissions: &'a [String],
+    /// Whether the application may inhibit anything
+    grant: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unknown: Vec<&'a str>,
+}
+
+/// What `permissions` let an application inhibit, all of it without an entry.
+fn allowed(permissions: Option<&[String]>) -> Vec<&'static str> {
+    match permissions {
+        None => FLAGS.to_vec(),
+        Some(permissions) => FLAGS
+            .into_iter()
+            .filter(|flag| permissions.iter().any(|permission| permission == flag))
+            .collect(),
+    }
+}
+
+/// The permissions the portal ignores, with a warning for each.
+fn unknown<'a>(app: &str, permissions: &'a [String]) -> Vec<&'a str> {
+    let unknown = tables::unknown_values(RESOURCE.table, permissions);
+    for permission in &unknown {
+        eprintln!("warning: {app} has the unknown inhibit flag {permission}");
+    }
+    unknown
+}
+
+pub async fn inhibit(
+    proxy: &Store<'_>,
+    action: InhibitAction,
+    app: Option<&str>,
+    flags: &[InhibitFlag],
+    format: Format,
+) -> ExitCode {
+    if action == InhibitAction::List {
+        return list(proxy, format).await;
+    }
+    let Some(app) = app else {
+        fail!(ErrorKind::InvalidInput, "expected <APP>");
+        return ExitCode::FAILURE;
+    };
+    if !check_app(app) {
+        return ExitCode::FAILURE;
+    }
+    let Some(current) = RESOURCE.permissions(proxy, app).await else {
+        return ExitCode::FAILURE;
+    };
+
+    let flags: Vec<&str> = if flags.is_empty() {
+        FLAGS.to_vec()
+    } else {
+        flags.iter().map(|flag| flag.stored()).collect()
+    };
+    // the flags not given stay as they are, all of them without an entry
+    let before = allowed(current.as_deref());
+    let keep = |flag: &str| match action {
+        InhibitAction::Allow => flags.contains(&flag) || before.contains(&flag),
+        _ => !flags.contains(&flag) && before.contains(&flag),
+    };
+    let permissions: Vec<String> = match action {
+        InhibitAction::Allow | InhibitAction::Deny => FLAGS
+            .into_iter()
+            .filter(|flag| keep(flag))
+            .map(String::from)
+            .collect(),
+        InhibitAction::Status => return print_status(app, current, format),
+        InhibitAction::List => unreachable!("handled above"),
+    };
+
+    if let Some(current) = &current {
+        unknown(app, current);
+    }
+    if !RESOURCE.set(proxy, app, &permissions).await {
+        return ExitCode::FAILURE;
+    }
+    if permissions.is_empty() {
+        status!("{app} may no longer inhibit anything without asking");
+    } else {
+        status!(
+            "{app} may inhibit {} without asking",
+            permissions.join(", ")
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+fn print_status(app: &str, permissions: Option<Vec<String>>, format: Format) -> ExitCode {
+    let allowed = allowed(permissions.as_deref());
+    if let Some(permissions) = &permissions {
+        unknown(app, permissions);
+    }
+    if format == Format::Json {
+        print_json(&Status {
+            app,
+            permissions,
+            allowed,
+        });
+        return ExitCode::SUCCESS;
+    }
+
+    match (&permissions, allowed.as_slice()) {
+        (None, _) => println!("{app} has no inhibit setting, it may inhibit anything"),
+        (Some(_), []) => println!("{app} may not inhibit anything without asking"),
+        (Some(_), allowed) => println!("{app} may inhibit {} without asking", allowed.join(", ")),
+    }
+    ExitCode::SUCCESS
+}
+
+/// Print every application, marking the ones that may keep the machine
+/// from logging out, switching users, suspending or idling.
+async fn list(proxy: &Store<'_>, format: Format) -> ExitCode {
+    let Some((apps, _)) = RESOURCE.entries(proxy).await else {
+        return ExitCode::FAILURE;
+    };
+    let apps: BTreeMap<String, Vec<String>> = apps.into_iter().collect();
+    let entries: BTreeMap<&str, Entry> = apps
+        .iter()
+        .map(|(app, permissions)| {
+            let entry = Entry {
+                permissions,
+                grant: !allowed(Some(permissions)).is_empty(),
+                unknown: unknown(app, permissions),
+            };
+            (app.as_str(), entry)
+        })
+        .collect();
+
+    if format == Format::Json {
+        print_json(&entries);
+        return ExitCode::SUCCESS;
+    }
+
+    let mut table = new_table();
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        table.set_header(vec!["AppID", "Inhibit", "Grant"]);
+    }
+    for (app, entry) in &entries {
+        let allowed = allowed(Some(entry.permissions));
+        let grant = if entry.grant {
+            Cell::new("yes").fg(Color::Yellow)
+        } else {
+            Cell::new("")
+        };
+        table.add_row(vec![
+            Cell::new(cell_text(app)),
+            Cell::new(allowed.join(",")),
+            grant,
+        ]);
+    }
+
+    println!("{table}");
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,49 @@
+//! Ctrl-C for the commands that run until stopped, such as `watch` and the
+//! REPL: the first one lets the current call finish and then stops, the
+//! second one exits right away.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+
+/// Exit code after an interrupt, which is what shells report for SIGINT.
+pub const EXIT_CODE: u8 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+static NOTIFY: Notify = Notify::const_new();
+
+/// Handle Ctrl-C from now on instead of dying at once.
+pub fn install() {
+    tokio::spawn(async {
+        loop {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                eprintln!("warning: cannot handle Ctrl-C: {e}");
+                return;
+            }
+            if INTERRUPTED.swap(true, Ordering::Relaxed) {
+                eprintln!("interrupted again, exiting");
+                std::process::exit(EXIT_CODE.into());
+            }
+            eprintln!(
+                "interrupted, stopping after the current call, press Ctrl-C again to exit now"
+            );
+            NOTIFY.notify_waiters();
+        }
+    });
+}
+
+/// Whether Ctrl-C was pressed, after which no new work should start.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Wait for Ctrl-C, returning at once if it was already pressed.
+pub async fn wait() {
+    // created first, so that an interrupt in between still wakes it
+    let notified = NOTIFY.notified();
+    if interrupted() {
+        return;
+    }
+    notified.await;
+}
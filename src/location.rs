@@ -0,0 +1,246 @@
+//! Shortcuts for the location table.
+//!
+//! xdg-desktop-portal keeps the choice of an application in its permission
+//! strings rather than in the data: the first is the granted accuracy as
+//! GeoClue names it, the second the last use in microseconds since the epoch.
+
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+
+use crate::error::ErrorKind;
+use crate::shortcut::{Resource, check_app};
+use crate::store::Store;
+use crate::{Format, LocationAccuracy, LocationAction, print_json};
+
+const RESOURCE: Resource = Resource {
+    table: "location",
+    id: "location",
+    what: "location",
+};
+
+/// The accuracy a denied application is stored with.
+const NONE: &str = "NONE";
+
+impl LocationAccuracy {
+    /// The name the portal stores, see `GClueAccuracyLevel`.
+    fn stored(self) -> &'static str {
+        match self {
+            LocationAccuracy::Country => "COUNTRY",
+            LocationAccuracy::City => "CITY",
+            LocationAccuracy::Neighborhood => "NEIGHBORHOOD",
+            LocationAccuracy::Street => "STREET",
+            LocationAccuracy::Exact => "EXACT",
+        }
+    }
+
+    /// The accuracy of a stored name, `None` for NONE and unknown names.
+    fn from_stored(stored: &str) -> Option<Self> {
+        [
+            LocationAccuracy::Country,
+            LocationAccuracy::City,
+            LocationAccuracy::Neighborhood,
+            LocationAccuracy::Street,
+            LocationAccuracy::Exact,
+        ]
+        .into_iter()
+        .find(|accuracy| accuracy.stored() == stored)
+    }
+}
+
+/// The accuracy and last use of an entry, `None` where it is malformed.
+fn decode(permissions: &[String]) -> (Option<&str>, Option<SystemTime>) {
+    let accuracy = permissions.first().map(String::as_str);
+    let last_used = permissions
+        .get(1)
+        .and_then(|last_used| last_used.parse::<u64>().ok())
+        .map(|micros| SystemTime::UNIX_EPOCH + Duration::from_micros(micros));
+    (accuracy, last_used)
+}
+
+/// The accuracy to allow, `accuracy` or else the one the user picked before,
+/// exact without either.
+fn allowed(accuracy: Option<LocationAccuracy>, current: Option<&[String]>) -> &'static str {
+    accuracy
+        .or_else(|| {
+            let current = current.and_then(|current| decode(current).0);
+            current.and_then(LocationAccuracy::from_stored)
+        })
+        .unwrap_or(LocationAccuracy::Exact)
+        .stored()
+}
+
+/// The last use to write back, what the portal wrote when well-formed.
+fn kept_last_use(current: Option<&[String]>) -> &str {
+    current
+        .and_then(|current| current.get(1))
+        .filter(|last_used| last_used.parse::<u64>().is_ok())
+        .map_or("0", String::as_str)
+}
+
+#[derive(Serialize, Debug)]
+struct Status<'a> {
+    app: &'a str,
+    /// `None` when the application has no entry
+    accuracy: Option<&'a str>,
+    /// RFC 3339, `None` when the application never used the location
+    last_used: Option<String>,
+    permissions: Option<&'a [String]>,
+}
+
+pub async fn location(
+    proxy: &Store<'_>,
+    action: LocationAction,
+    app: &str,
+    accuracy: Option<LocationAccuracy>,
+    format: Format,
+) -> ExitCode {
+    if !check_app(app) {
+        return ExitCode::FAILURE;
+    }
+    let Some(current) = RESOURCE.permissions(proxy, app).await else {
+        return ExitCode::FAILURE;
+    };
+
+    let stored = match action {
+        // keep the accuracy the user picked before unless told otherwise
+        LocationAction::Allow => allowed(accuracy, current.as_deref()),
+        LocationAction::Deny => {
+            if accuracy.is_some() {
+                fail!(ErrorKind::InvalidInput, "--accuracy only applies to allow");
+                return ExitCode::FAILURE;
+            }
+            NONE
+        }
+        LocationAction::Status => return print_status(app, current.as_deref(), format),
+    };
+
+    // the portal updates the last use itself, keep what it wrote
+    let last_used = kept_last_use(current.as_deref());
+    let permissions = [stored.to_string(), last_used.to_string()];
+    if !RESOURCE.set(proxy, app, &permissions).await {
+        return ExitCode::FAILURE;
+    }
+    if stored == NONE {
+        status!("{app} may no longer access the location");
+    } else {
+        status!(
+            "{app} may access the location at {} accuracy",
+            stored.to_lowercase()
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+fn print_status(app: &str, permissions: Option<&[String]>, format: Format) -> ExitCode {
+    let (accuracy, last_used) = permissions.map(decode).unwrap_or_default();
+    // a zero timestamp is what an entry never used carries
+    let last_used = last_used.filter(|last_used| *last_used != SystemTime::UNIX_EPOCH);
+    if format == Format::Json {
+        print_json(&Status {
+            app,
+            accuracy,
+            last_used: last_used
+                .map(|last_used| humantime::format_rfc3339_seconds(last_used).to_string()),
+            permissions,
+        });
+        return ExitCode::SUCCESS;
+    }
+
+    match accuracy {
+        None => println!("{app} has no location setting, the portal will ask"),
+        Some(NONE) => println!("{app} may not access the location"),
+        Some(accuracy) if LocationAccuracy::from_stored(accuracy).is_some() => println!(
+            "{app} may access the location at {} accuracy",
+            accuracy.to_lowercase()
+        ),
+        Some(accuracy) => println!("warning: {app} has the unknown location accuracy {accuracy}"),
+    }
+    match (permissions, last_used) {
+        (None, _) => {}
+        (Some(_), Some(last_used)) => {
+            let ago = SystemTime::now()
+                .duration_since(last_used)
+                .map(|ago| Duration::from_secs(ago.as_secs()));
+            match ago {
+                Ok(ago) => println!(
+                    "last used: {} ({} ago)",
+                    humantime::format_rfc3339_seconds(last_used),
+                    humantime::format_duration(ago)
+                ),
+                Err(_) => println!(
+                    "last used: {}",
+                    humantime::format_rfc3339_seconds(last_used)
+                ),
+            }
+        }
+        (Some(permissions), None) if permissions.len() < 2 => {
+            println!("warning: the entry has no last use, the portal will ignore it")
+        }
+        (Some(_), None) => println!("last used: never"),
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(permissions: &[&str]) -> Vec<String> {
+        permissions.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn accuracies_round_trip() {
+        for accuracy in [
+            LocationAccuracy::Country,
+            LocationAccuracy::City,
+            LocationAccuracy::Neighborhood,
+            LocationAccuracy::Street,
+            LocationAccuracy::Exact,
+        ] {
+            assert_eq!(
+                LocationAccuracy::from_stored(accuracy.stored()),
+                Some(accuracy)
+            );
+        }
+        assert_eq!(LocationAccuracy::from_stored(NONE), None);
+        assert_eq!(LocationAccuracy::from_stored("exact"), None);
+        assert_eq!(LocationAccuracy::from_stored(""), None);
+    }
+
+    #[test]
+    fn decode_tolerates_malformed_entries() {
+        let used = SystemTime::UNIX_EPOCH + Duration::from_micros(1_700_000_000_000_000);
+        assert_eq!(
+            decode(&entry(&["CITY", "1700000000000000"])),
+            (Some("CITY"), Some(used))
+        );
+        assert_eq!(decode(&entry(&["CITY", "yesterday"])), (Some("CITY"), None));
+        assert_eq!(decode(&entry(&["CITY"])), (Some("CITY"), None));
+        assert_eq!(decode(&[]), (None, None));
+    }
+
+    #[test]
+    fn allow_keeps_the_picked_accuracy() {
+        let city = entry(&["CITY", "5"]);
+        assert_eq!(allowed(None, Some(&city)), "CITY");
+        assert_eq!(
+            allowed(Some(LocationAccuracy::Street), Some(&city)),
+            "STREET"
+        );
+        // denied or unknown is not a pick to keep
+        assert_eq!(allowed(None, Some(&entry(&[NONE, "5"]))), "EXACT");
+        assert_eq!(allowed(None, Some(&entry(&["FAR", "5"]))), "EXACT");
+        assert_eq!(allowed(None, None), "EXACT");
+    }
+
+    #[test]
+    fn the_last_use_is_kept_when_well_formed() {
+        assert_eq!(kept_last_use(Some(&entry(&["CITY", "42"]))), "42");
+        assert_eq!(kept_last_use(Some(&entry(&["CITY", "-1"]))), "0");
+        assert_eq!(kept_last_use(Some(&entry(&["CITY"]))), "0");
+        assert_eq!(kept_last_use(None), "0");
+    }
+}
@@ -1,7 +1,89 @@
-use clap::{Args, Parser, Subcommand};
-use comfy_table::Table;
-use std::collections::HashMap;
-use zbus::{Connection, proxy, zvariant::OwnedValue};
+/// Print an informational message, which `--quiet` suppresses.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
+        } else if $crate::output::redirected() || $crate::error::json() {
+            // keep the output on stdout a single document
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Report a failure caused by `$error`, see `error::report`.
+macro_rules! fail {
+    ($error:expr, $($arg:tt)*) => {
+        $crate::error::report($crate::error::Classify::kind(&$error), format_args!($($arg)*))
+    };
+}
+
+mod apps;
+mod audit;
+mod background;
+mod bench;
+mod changes;
+mod config;
+mod conflict;
+mod csv;
+mod data;
+mod devices;
+mod error;
+mod filter;
+mod inhibit;
+mod interrupt;
+mod location;
+mod matrix;
+mod migrate;
+mod notifications;
+mod output;
+mod screenshot;
+mod search;
+mod shortcut;
+mod show;
+mod snapshot;
+mod stats;
+mod store;
+mod tables;
+mod variant;
+mod verify;
+mod wallpaper;
+mod watch;
+
+use changes::{Change, ChangeLog, ShowDiffArgs};
+use clap::{ArgAction, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use comfy_table::{
+    Attribute, Cell, Color, ContentArrangement, Table,
+    modifiers::UTF8_ROUND_CORNERS,
+    presets::{ASCII_MARKDOWN, NOTHING, UTF8_FULL},
+};
+use conflict::ConflictWatch;
+use error::ErrorKind;
+use filter::{Glob, IdFilterArgs, MatchArgs};
+use futures::stream::{self, Stream, StreamExt};
+use rustyline::{DefaultEditor, error::ReadlineError};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU16, Ordering};
+use std::sync::{Mutex, PoisonError};
+use store::Store;
+use tracing::Level;
+use tracing_subscriber::{
+    Layer,
+    fmt::{self, format::FmtSpan},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+};
+use verify::VerifyArgs;
+use zbus::{
+    Connection, proxy,
+    zvariant::{OwnedValue, Value},
+};
 
 // Cli struct
 
@@ -9,229 +91,4794 @@ use zbus::{Connection, proxy, zvariant::OwnedValue};
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
+    /// The format of the printed results
+    #[arg(long, value_enum, global = true, default_value_t = Format::Table)]
+    format: Format,
+
+    /// Log D-Bus calls to stderr, repeat for their replies too
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Do not print informational messages
+    #[arg(short, long, global = true, default_value_t = false)]
+    quiet: bool,
+
+    /// Do not print the header row of list, get and lookup tables
+    #[arg(long, global = true, default_value_t = false)]
+    no_header: bool,
+
+    /// The borders of printed tables
+    #[arg(long, value_enum, global = true, default_value_t = TableStyle::Boxed)]
+    style: TableStyle,
+
+    /// Wrap tables to this many columns, defaults to the terminal width
+    #[arg(long, global = true, value_name = "COLUMNS")]
+    max_width: Option<u16>,
+
+    /// Append a JSON record of every write to this file
+    #[arg(long, global = true)]
+    audit_log: Option<String>,
+
+    /// Write the results to this file instead of stdout
+    #[arg(short, long, global = true, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Create the parent directory of the output file if missing
+    #[arg(long, global = true, default_value_t = false)]
+    mkdir: bool,
+
+    /// Never page list and lookup results that do not fit the terminal
+    #[arg(long, global = true, default_value_t = false)]
+    no_pager: bool,
+
+    /// When to color the output, auto honors NO_COLOR
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Accept application IDs that break the Flatpak rules, which the
+    /// permission store does not enforce itself
+    #[arg(long, global = true, default_value_t = false)]
+    allow_invalid_app_id: bool,
+
+    /// Print how long every call to the permission store takes
+    #[arg(long, global = true, default_value_t = false)]
+    timing: bool,
+
     #[command(subcommand)]
     command: Subcommands,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Table,
+    /// JSON, failures are reported on stderr as `{"error": {"kind", "message"}}` lines
+    Json,
+    /// GitHub-flavored Markdown tables
+    Markdown,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorChoice {
+    /// When printing to a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum TableStyle {
+    /// ASCII borders around every cell
+    Boxed,
+    /// No borders, columns separated by spaces
+    Plain,
+    /// Box-drawing borders around every cell
+    Utf8,
+    /// Box-drawing borders with rounded corners
+    Rounded,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SortBy {
+    /// By application ID
+    App,
+    /// By number of permissions, most first
+    Count,
+}
+
+impl SortBy {
+    /// Put the entries of a resource in this order.
+    fn sort(self, apps: &mut [(&String, &Vec<String>)]) {
+        apps.sort_by_key(|(app, _)| *app);
+        if self == SortBy::Count {
+            // stable, so equal counts stay sorted by application
+            apps.sort_by_key(|(_, permissions)| Reverse(permissions.len()));
+        }
+    }
+}
+
+/// The order of `list`, IDs and applications compare byte by byte.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ListSort {
+    /// By resource ID
+    Id,
+    /// Numeric resource IDs by value, before the other ones
+    Numeric,
+    /// The order the permission store returned
+    None,
+    /// With --details, the applications of a resource by their permissions
+    Permissions,
+}
+
+impl ListSort {
+    /// Put the resource IDs of a table in this order.
+    fn sort(self, ids: &mut [String]) {
+        match self {
+            ListSort::Numeric => {
+                // numbers first, by value, then everything else as text
+                ids.sort_by_cached_key(|id| {
+                    (id.parse::<u64>().map_err(|_| id.clone()), id.clone())
+                });
+            }
+            ListSort::None => {}
+            ListSort::Id | ListSort::Permissions => ids.sort(),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum BackgroundAction {
+    /// Let the application run in the background
+    Allow,
+    /// Stop the application from running in the background
+    Deny,
+    /// Have the portal ask the user every time
+    Ask,
+    /// Print the setting and data of the application
+    Status,
+    /// Print the setting of every application
+    List,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum DeviceAction {
+    /// Let the application use the device
+    Allow,
+    /// Stop the application from using the device
+    Deny,
+    /// Have the portal ask the user every time
+    Ask,
+    /// Print the setting of the application, or of every application
+    Status,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Device {
+    Camera,
+    Microphone,
+    Speakers,
+    /// The camera, the microphone and the speakers
+    All,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum InhibitAction {
+    /// Let the application inhibit without asking
+    Allow,
+    /// Stop the application from inhibiting without asking
+    Deny,
+    /// Print what the application may inhibit
+    Status,
+    /// Print every application, marking the ones holding a grant
+    List,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum InhibitFlag {
+    Logout,
+    /// Switching users
+    Switch,
+    Suspend,
+    /// Marking the session idle
+    Idle,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum LocationAction {
+    /// Let the application access the location
+    Allow,
+    /// Stop the application from accessing the location
+    Deny,
+    /// Print the accuracy and last use of the application
+    Status,
+}
+
+/// How precisely the portal reveals the location, coarsest first.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum LocationAccuracy {
+    Country,
+    City,
+    Neighborhood,
+    Street,
+    Exact,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum NotificationAction {
+    /// Let the application send notifications
+    Allow,
+    /// Stop the application from sending notifications
+    Deny,
+    /// Forget the choice, going back to the portal default
+    Reset,
+    /// Print whether the application may send notifications
+    Status,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ScreenshotAction {
+    /// Let the application take screenshots without the dialog
+    Allow,
+    /// Stop the application from taking screenshots
+    Deny,
+    /// Forget the choice, so that the portal shows the dialog again
+    Reset,
+    /// Print what the portal does when the application takes a screenshot
+    Status,
+    /// Print the setting of every application
+    List,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum WallpaperAction {
+    /// Let the application set the wallpaper without asking
+    Allow,
+    /// Stop the application from setting the wallpaper
+    Deny,
+    /// Print whether the application may set the wallpaper
+    Status,
+    /// Print the setting of every application
+    List,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Column {
+    App,
+    Permissions,
+    Count,
+}
+
 #[derive(Subcommand, Debug)]
 enum Subcommands {
+    /// List the applications that have entries in a table
+    Apps(AppsArgs),
+
+    /// Report which applications hold a permission
+    Audit(AuditArgs),
+
+    /// Allow, deny or check running in the background for an application
+    Background(BackgroundArgs),
+
+    /// Measure how long lookups of a resource take
+    #[command(hide = true)]
+    BenchLookup(BenchLookupArgs),
+
+    /// Check whether an application holds a permission
+    Check(CheckArgs),
+
+    /// Delete every resource of a table
+    #[command(visible_alias = "reset")]
+    ClearTable(ClearTableArgs),
+
+    /// Compare two table snapshots without touching the permission store
+    Compare(CompareArgs),
+
+    /// Compare the entries of several applications side by side
+    CompareApps(CompareAppsArgs),
+
+    /// Copy an application's permissions to another application
+    CopyApp(CopyAppArgs),
+
     /// Delete Permissions
+    #[command(visible_alias = "rm")]
     Delete(DeleteArgs),
 
+    /// Allow, deny or check the camera, microphone and speakers of an application
+    Devices(DevicesArgs),
+
+    /// Compare the permissions of two applications
+    Diff(DiffArgs),
+
+    /// Describe the well-known tables, their resources and permission values
+    Explain(ExplainArgs),
+
+    /// Write the entries of a table as CSV, one row per application
+    ExportCsv(ExportCsvArgs),
+
     /// Get Permissions
+    #[command(visible_alias = "g")]
     Get(GetArgs),
 
+    /// Search a table for applications holding a permission
+    Grep(GrepArgs),
+
+    /// Replace the entries of the resources in a CSV written by export-csv
+    ImportCsv(ImportCsvArgs),
+
+    /// Allow, deny or check inhibiting logout, suspend and idle for an application
+    Inhibit(InhibitArgs),
+
     /// List Permissions
+    #[command(visible_alias = "ls")]
     List(ListArgs),
 
+    /// Allow, deny or check the location access of an application
+    Location(LocationArgs),
+
     /// Lookup Permissions
+    #[command(visible_alias = "lu")]
     Lookup(LookupArgs),
 
+    /// Print a table as a grid of resources by applications
+    Matrix(MatrixArgs),
+
+    /// Merge an application's permissions into another application
+    Merge(MergeArgs),
+
+    /// Move permissions to new application IDs across tables
+    Migrate(MigrateArgs),
+
+    /// Move a resource to a new ID
+    Move(MoveArgs),
+
+    /// Allow, deny, reset or check the notifications of an application
+    Notifications(NotificationsArgs),
+
+    /// Remove an application from every resource of a table
+    #[command(visible_alias = "delete-app")]
+    PurgeApp(PurgeAppArgs),
+
+    /// Move every entry of an application to a new application ID
+    MigrateApp(MigrateAppArgs),
+
+    /// Rename an application on a single resource
+    RenameApp(RenameAppArgs),
+
+    /// Start an interactive shell
+    Repl,
+
+    /// Write the resources of a snapshot back, associated data included
+    Restore(RestoreArgs),
+
+    /// Revoke Permissions
+    Revoke(RevokeArgs),
+
+    /// Allow, deny, reset or check taking screenshots for an application
+    Screenshot(ScreenshotArgs),
+
+    /// Search the entries of a table by resource, application and permission
+    Search(SearchArgs),
+
     /// Set Permissions
+    #[command(visible_alias = "s")]
     Set(SetArgs),
+
+    /// Show everything an application has been granted
+    Show(ShowArgs),
+
+    /// Write a JSON snapshot of a whole table
+    #[command(visible_alias = "dump")]
+    Snapshot(SnapshotArgs),
+
+    /// Summarize the contents of tables
+    Stats(StatsArgs),
+
+    /// List the well-known portal tables
+    Tables(TablesArgs),
+
+    /// Allow, deny or check setting the wallpaper for an application
+    Wallpaper(WallpaperArgs),
+
+    /// Print the changes to the permission store as they happen
+    Watch(WatchArgs),
+
+    /// Find every resource an application has permissions on
+    Where(WhereArgs),
 }
 
 #[derive(Args, Debug)]
-struct LookupArgs {
+struct AppsArgs {
+    /// Also print the resources each application appears under
+    #[arg(long, default_value_t = false)]
+    resources: bool,
+
+    /// Sort by application ID instead of by resource count
+    #[arg(long, default_value_t = false)]
+    by_name: bool,
+
     /// The name of the table to use
     table: String,
-
-    /// The resource ID to modify
-    id: String,
 }
 
 #[derive(Args, Debug)]
-struct ListArgs {
-    /// The name of the table to use
-    table: String,
+struct AuditArgs {
+    /// Report the applications that do not hold the permission instead
+    #[arg(long, default_value_t = false)]
+    not: bool,
+
+    /// Audit every well-known table
+    #[arg(long, default_value_t = false)]
+    all_tables: bool,
+
+    /// The name of the table (omitted with --all-tables) and the permission
+    #[arg(value_names = ["TABLE", "PERMISSION"], num_args = 1..=2, required = true)]
+    targets: Vec<String>,
+}
+
+impl AuditArgs {
+    /// Split the positionals into the tables to scan and the permission.
+    fn resolve(&self) -> Option<(Vec<String>, &str)> {
+        match (self.all_tables, self.targets.as_slice()) {
+            (false, [table, permission]) => Some((vec![table.clone()], permission)),
+            (true, [permission]) => Some((known_tables(), permission)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Args, Debug)]
-struct GetArgs {
+struct CheckArgs {
+    /// Another permission value to accept
+    #[arg(long = "value")]
+    values: Vec<String>,
+
+    /// Print the stored permissions, or the lack of an entry, which the
+    /// exit code alone does not tell
+    #[arg(long, default_value_t = false)]
+    print_value: bool,
+
     /// The name of the table to use
     table: String,
 
-    /// The resource ID to modify
+    /// The resource ID to check
     id: String,
 
     /// Name of the application
     app: String,
+
+    /// The permission to look for
+    #[arg(required_unless_present = "values")]
+    permission: Option<String>,
 }
 
 #[derive(Args, Debug)]
-struct DeleteArgs {
+struct GrepArgs {
+    /// Print the entries that do not hold the permission instead
+    #[arg(long, default_value_t = false)]
+    invert: bool,
+
+    #[command(flatten)]
+    matching: MatchArgs,
+
     /// The name of the table to use
     table: String,
 
-    /// The resource ID to modify
-    id: String,
-
-    /// Name of the application
-    app: Option<String>,
+    /// The permission to search for
+    permission: String,
 }
 
 #[derive(Args, Debug)]
-struct SetArgs {
-    /// Whether to create the table if it does not exist
+struct ClearTableArgs {
+    /// Only print the resources that would be deleted
+    #[arg(short = 'n', long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Do not ask for confirmation
     #[arg(short, long, default_value_t = false)]
-    create: bool,
+    yes: bool,
 
-    /// The name of the table to use
+    #[command(flatten)]
+    filter: IdFilterArgs,
+
+    #[command(flatten)]
+    matching: MatchArgs,
+
+    /// The name of the table to clear
     table: String,
+}
 
-    /// The resource ID to modify
-    id: String,
+#[derive(Args, Debug)]
+struct CompareArgs {
+    /// Exit successfully even if the snapshots differ
+    #[arg(long, default_value_t = false)]
+    exit_zero: bool,
 
-    /// The application ID to modify
-    app: String,
+    /// The older snapshot
+    before: String,
 
-    /// The permissions to set
-    permissions: Vec<String>,
+    /// The newer snapshot
+    after: String,
 }
 
-// custom DBus type
-
-type LookupResponse = (HashMap<String, Vec<String>>, OwnedValue);
+#[derive(Args, Debug)]
+struct CompareAppsArgs {
+    /// Another table to scan besides the well-known portal tables, may be repeated
+    #[arg(long = "table", value_name = "TABLE", value_delimiter = ',')]
+    tables: Vec<String>,
 
-#[proxy(
-    interface = "org.freedesktop.impl.portal.PermissionStore",
-    default_service = "org.freedesktop.impl.portal.PermissionStore",
-    default_path = "/org/freedesktop/impl/portal/PermissionStore",
-    gen_async = true
-)]
-trait PermissionStore {
-    #[zbus(property(emits_changed_signal = "const"), name = "version")]
-    fn version(&self) -> zbus::Result<u32>;
+    /// Only print the resources where the applications differ
+    #[arg(long, default_value_t = false)]
+    diff_only: bool,
 
-    fn delete(&self, table: &str, id: &str) -> zbus::Result<()>;
-    fn delete_permission(&self, table: &str, id: &str, app: &str) -> zbus::Result<()>;
-    fn get_permission(&self, table: &str, id: &str, app: &str) -> zbus::Result<Vec<String>>;
-    fn list(&self, table: &str) -> zbus::Result<Vec<String>>;
-    fn lookup(&self, table: &str, id: &str) -> zbus::Result<LookupResponse>;
-    fn set_permission(
-        &self,
-        table: &str,
-        create: bool,
-        id: &str,
-        app: &str,
-        permissions: &[String],
-    ) -> zbus::Result<()>;
-    fn set_value(&self, create: bool, id: &str, data: OwnedValue) -> zbus::Result<()>;
+    /// The applications to compare
+    #[arg(num_args = 2.., required = true)]
+    apps: Vec<String>,
 }
 
-// main impl
+#[derive(Args, Debug)]
+struct CopyAppArgs {
+    /// Copy on every resource the source application appears in
+    #[arg(long, default_value_t = false)]
+    all_ids: bool,
 
-const PERMISSION_STORE_SPEC_VER: u32 = 2;
+    /// Delete the source application's entry afterwards
+    #[arg(long = "move", default_value_t = false)]
+    remove_source: bool,
+
+    /// Replace an existing entry of the destination application
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
 
-async fn delete_permission(
-    proxy: &PermissionStoreProxy<'_>,
-    args: &DeleteArgs,
-) -> zbus::Result<()> {
-    match &args.app {
-        Some(app) => proxy.delete_permission(&args.table, &args.id, app).await,
-        None => proxy.delete(&args.table, &args.id).await,
+    #[command(flatten)]
+    diff: ShowDiffArgs,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource ID (omitted with --all-ids), source and destination application IDs
+    #[arg(value_names = ["ID", "SRC_APP", "DST_APP"], num_args = 2..=3, required = true)]
+    targets: Vec<String>,
+}
+
+impl CopyAppArgs {
+    /// Split the positionals into the resource ID, the source and the destination app.
+    fn resolve(&self) -> Option<(Option<&str>, &str, &str)> {
+        match (self.all_ids, self.targets.as_slice()) {
+            (false, [id, src, dst]) => Some((Some(id), src, dst)),
+            (true, [src, dst]) => Some((None, src, dst)),
+            _ => None,
+        }
     }
 }
 
-fn print_lookup_response(response: &LookupResponse) {
-    let mut table = Table::new();
-    table.set_header(vec!["AppID", "Permissions"]);
+#[derive(Args, Debug)]
+struct LookupArgs {
+    /// Fail if any of the resources is missing, not only all of them
+    #[arg(long, default_value_t = false)]
+    strict: bool,
 
-    for (app_id, allowed) in response.0.iter() {
-        table.add_row(vec![app_id, &allowed.join(",")]);
-    }
+    /// Also print how many permissions every application holds
+    #[arg(long, default_value_t = false)]
+    counts: bool,
 
-    println!("{table}");
-    println!("associated data:\n{:?}", response.1);
-}
+    /// The order of the applications
+    #[arg(long, visible_alias = "sort-by", value_enum, default_value_t = SortBy::App)]
+    sort: SortBy,
 
-fn print_list_response(response: &[String]) {
-    let mut table = Table::new();
-    table.set_header(vec!["Resource ID"]);
+    /// Print the applications in the opposite order
+    #[arg(long, default_value_t = false)]
+    reverse: bool,
 
-    for id in response.iter() {
-        table.add_row(vec![id]);
-    }
+    /// The columns to print, defaults to app,permissions and count with --counts
+    #[arg(long, value_enum, value_delimiter = ',')]
+    columns: Vec<Column>,
 
-    println!("{table}");
+    /// Only print the applications matching this glob, can be repeated
+    #[arg(long = "app", value_name = "APP")]
+    apps: Vec<String>,
+
+    /// Do not look for what was meant when the table or resource is missing
+    #[arg(long, default_value_t = false)]
+    no_suggest: bool,
+
+    #[command(flatten)]
+    matching: MatchArgs,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource IDs to look up
+    #[arg(required = true)]
+    ids: Vec<String>,
 }
 
-fn print_get_permission_response(response: &[String]) {
-    let mut table = Table::new();
-    table.set_header(vec!["Permission"]);
+#[derive(Args, Debug)]
+struct ListArgs {
+    /// Look up every resource and print its entries
+    #[arg(long, default_value_t = false)]
+    details: bool,
 
-    for permission in response.iter() {
-        table.add_row(vec![permission]);
-    }
+    /// Like --details, and also print the associated data
+    #[arg(long, default_value_t = false)]
+    with_data: bool,
 
-    println!("{table}");
+    /// The order of the resources
+    #[arg(long, value_enum, default_value_t = ListSort::Id)]
+    sort: ListSort,
+
+    /// Print the rows in the opposite order
+    #[arg(long, default_value_t = false)]
+    reverse: bool,
+
+    /// Same as --sort none
+    #[arg(long, default_value_t = false, conflicts_with_all = ["sort", "numeric_sort"])]
+    no_sort: bool,
+
+    /// Same as --sort numeric
+    #[arg(long, default_value_t = false, conflicts_with = "sort")]
+    numeric_sort: bool,
+
+    /// Print at most this many resources
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Only print how many resources matched
+    #[arg(long, default_value_t = false, conflicts_with_all = ["details", "with_data", "limit"])]
+    count: bool,
+
+    /// Only keep the resources without any application entry
+    #[arg(long, default_value_t = false)]
+    empty: bool,
+
+    /// With --empty, also require the resource to have no data set
+    #[arg(long, default_value_t = false, requires = "empty")]
+    no_data_only: bool,
+
+    #[command(flatten)]
+    filter: IdFilterArgs,
+
+    #[command(flatten)]
+    matching: MatchArgs,
+
+    /// The names of the tables to use, several ones are listed together
+    #[arg(required_unless_present = "table_options")]
+    tables: Vec<String>,
+
+    /// Another table to list, may be repeated
+    #[arg(long = "table", value_name = "TABLE")]
+    table_options: Vec<String>,
 }
 
-#[tokio::main]
-async fn main() {
-    let connection = match Connection::session().await {
-        Ok(connection) => connection,
-        Err(e) => {
-            eprintln!("Failed to connect: {e}");
-            return;
+impl ListArgs {
+    /// The positional tables followed by the `--table` ones.
+    fn tables(&self) -> Vec<&str> {
+        let mut tables: Vec<&str> = Vec::new();
+        for table in self.tables.iter().chain(&self.table_options) {
+            if !tables.contains(&table.as_str()) {
+                tables.push(table);
+            }
         }
-    };
+        tables
+    }
 
-    let proxy = match PermissionStoreProxy::new(&connection).await {
-        Ok(proxy) => proxy,
-        Err(e) => {
-            eprintln!("Failed to create proxy: {e}");
-            return;
+    fn sort(&self) -> ListSort {
+        match (self.no_sort, self.numeric_sort) {
+            (true, _) => ListSort::None,
+            (_, true) => ListSort::Numeric,
+            _ => self.sort,
         }
-    };
+    }
+}
 
-    let server_version = match proxy.version().await {
-        Ok(version) => version,
-        Err(e) => {
-            eprintln!("Failed to get server version: {e}");
-            return;
-        }
-    };
+#[derive(Args, Debug)]
+struct GetArgs {
+    /// The name of the table to use
+    table: String,
 
-    if server_version != PERMISSION_STORE_SPEC_VER {
-        eprintln!(
-            "Server version {server_version} does not match expected version {PERMISSION_STORE_SPEC_VER}"
-        );
-        return;
-    }
+    /// The resource ID to modify
+    id: String,
 
-    let cli = Cli::parse();
-    match &cli.command {
-        Subcommands::Delete(args) => match delete_permission(&proxy, args).await {
-            Ok(_) => println!("Permissions deleted successfully"),
-            Err(e) => eprintln!("failed to delete permissions: {e}"),
-        },
-        Subcommands::Get(GetArgs { table, id, app }) => {
-            match proxy.get_permission(table, id, app).await {
-                Ok(permissions) => print_get_permission_response(&permissions),
-                Err(e) => eprintln!("failed to get permissions: {e}"),
+    /// Name of the application, several ones are printed side by side
+    #[arg(required_unless_present = "app_options")]
+    apps: Vec<String>,
+
+    /// Another application to print, may be repeated
+    #[arg(long = "app", value_name = "APP")]
+    app_options: Vec<String>,
+
+    /// Treat the resource ID as a glob and print every matching resource
+    #[arg(long, default_value_t = false)]
+    glob: bool,
+
+    /// Print a row of permissions per application instead of side by side,
+    /// an empty one for applications without an entry
+    #[arg(long, default_value_t = false)]
+    by_app: bool,
+
+    /// Do not look for what was meant when the table or resource is missing
+    #[arg(long, default_value_t = false)]
+    no_suggest: bool,
+
+    #[command(flatten)]
+    matching: MatchArgs,
+}
+
+impl GetArgs {
+    /// The positional applications followed by the `--app` ones.
+    fn apps(&self) -> Vec<&str> {
+        let mut apps: Vec<&str> = Vec::new();
+        for app in self.apps.iter().chain(&self.app_options) {
+            if !apps.contains(&app.as_str()) {
+                apps.push(app);
             }
         }
-        Subcommands::List(ListArgs { table }) => match proxy.list(table).await {
-            Ok(ids) => print_list_response(&ids),
-            Err(e) => eprintln!("failed to list permissions: {e}"),
-        },
-        Subcommands::Lookup(LookupArgs { table, id }) => match proxy.lookup(table, id).await {
-            Ok(result) => print_lookup_response(&result),
-            Err(e) => eprintln!("failed to lookup permissions: {e}"),
-        },
-        Subcommands::Set(args) => match proxy
-            .set_permission(
-                &args.table,
-                args.create,
+        apps
+    }
+}
+
+#[derive(Args, Debug)]
+struct DeleteArgs {
+    /// Only delete the entries of this application or glob, may be repeated
+    #[arg(long = "app", value_name = "APP", value_delimiter = ',')]
+    apps: Vec<String>,
+
+    /// Do not ask for confirmation before deleting whole resources
+    #[arg(short, long, default_value_t = false)]
+    yes: bool,
+
+    /// Fail if an application has no entry to delete
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+
+    /// Succeed when there is nothing to delete, failing only on real errors
+    #[arg(long, default_value_t = false, conflicts_with = "strict")]
+    if_exists: bool,
+
+    /// Treat the resource IDs as globs and delete from every matching resource
+    #[arg(long, default_value_t = false)]
+    glob: bool,
+
+    /// Delete every application an --app glob matches, not only a single one
+    #[arg(long, default_value_t = false)]
+    force: bool,
+
+    #[command(flatten)]
+    matching: MatchArgs,
+
+    #[command(flatten)]
+    diff: ShowDiffArgs,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource IDs to modify
+    #[arg(required = true)]
+    ids: Vec<String>,
+}
+
+impl DeleteArgs {
+    /// The resource IDs and the applications to delete.
+    ///
+    /// `delete <table> <id> <app>` used to take the application as a positional
+    /// argument. Two IDs the last of which is a valid application ID could be
+    /// either form, so they are rejected rather than guessed.
+    fn resolve(&self) -> Result<(&[String], Vec<&str>), String> {
+        match (self.apps.as_slice(), self.ids.as_slice()) {
+            ([], [_, app]) if is_valid_app_id(app) => Err(format!(
+                "{app} could be an application or a resource ID: pass --app {app} to delete \
+                 the entries of the application, or put another ID last to delete the resources"
+            )),
+            (apps, ids) => Ok((ids, apps.iter().map(String::as_str).collect())),
+        }
+    }
+}
+
+/// Application IDs are reverse-DNS names, resource IDs usually are not.
+/// Whether `app` looks like a D-Bus style application ID such as
+/// `org.gnome.Maps`: at least three dot-separated elements made of ASCII
+/// letters, digits and `_`, none starting with a digit. Only the last
+/// element may also contain `-`.
+fn is_valid_app_id(app: &str) -> bool {
+    let elements: Vec<&str> = app.split('.').collect();
+    let last = elements.len().saturating_sub(1);
+    elements.len() >= 3
+        && app.len() <= 255
+        && elements.iter().enumerate().all(|(i, element)| {
+            !element.is_empty()
+                && !element.starts_with(|c: char| c.is_ascii_digit())
+                && element
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || (c == '-' && i == last))
+        })
+}
+
+/// Whether every one of `apps` is a valid application ID, reporting the
+/// ones that are not. The empty ID some tables keep defaults under is fine.
+fn check_app_ids<'a>(apps: impl IntoIterator<Item = &'a str>) -> bool {
+    if ALLOW_INVALID_APP_ID.load(Ordering::Relaxed) {
+        return true;
+    }
+    let mut valid = true;
+    for app in apps {
+        if app.is_empty() || is_valid_app_id(app) {
+            continue;
+        }
+        let e = format!(
+            "{app} is not a valid application ID, expected a reverse-DNS name such as \
+             org.gnome.Maps: at least three dot-separated elements of ASCII letters, digits \
+             and _, none starting with a digit, and - only in the last one; pass \
+             --allow-invalid-app-id to use it anyway"
+        );
+        fail!(e, "{e}");
+        valid = false;
+    }
+    valid
+}
+
+#[derive(Args, Debug)]
+struct SearchArgs {
+    /// Only match applications whose ID matches this glob
+    #[arg(long)]
+    app: Option<String>,
+
+    /// Only match entries holding this permission
+    #[arg(long)]
+    permission: Option<String>,
+
+    /// Only match resources whose ID matches this glob
+    #[arg(long)]
+    id: Option<String>,
+
+    /// Search every well-known table instead of a single one
+    #[arg(long, default_value_t = false, conflicts_with = "table")]
+    all_tables: bool,
+
+    #[command(flatten)]
+    matching: MatchArgs,
+
+    /// The name of the table to use
+    #[arg(required_unless_present = "all_tables")]
+    table: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct SetArgs {
+    /// Whether to create the table if it does not exist
+    #[arg(short, long, default_value_t = false)]
+    create: bool,
+
+    /// A resource ID to modify instead of the positional one, may be repeated
+    #[arg(long = "id", value_name = "ID", value_delimiter = ',')]
+    ids: Vec<String>,
+
+    /// An application ID to modify instead of the positional one, may be repeated
+    #[arg(long = "app", value_name = "APP", value_delimiter = ',')]
+    apps: Vec<String>,
+
+    /// Stop at the first resource that fails
+    #[arg(long, default_value_t = false)]
+    fail_fast: bool,
+
+    /// Refuse to write permissions the portal owning a well-known table
+    /// would not understand instead of warning about them, and with
+    /// --if-absent fail on entries that are present. The default with
+    /// strict in the configuration file
+    #[arg(long, default_value_t = false, overrides_with = "no_strict")]
+    strict: bool,
+
+    /// Only warn, whatever the configuration file says
+    #[arg(long, default_value_t = false, overrides_with = "strict")]
+    no_strict: bool,
+
+    /// Replace permissions without asking, even when it drops some
+    #[arg(short, long, default_value_t = false)]
+    force: bool,
+
+    /// Store an empty permission list when no permissions are given
+    #[arg(long, default_value_t = false)]
+    allow_empty: bool,
+
+    /// Skip applications without an entry instead of creating one
+    #[arg(long, default_value_t = false, conflicts_with = "create")]
+    if_exists: bool,
+
+    /// Leave applications that have an entry alone and only create new
+    /// ones. Another client may still write in between, which --verify
+    /// catches when it reads back
+    #[arg(long, default_value_t = false, conflicts_with = "if_exists")]
+    if_absent: bool,
+
+    /// Only write the permissions when they differ from the stored ones.
+    /// The default with if-changed in the configuration file
+    #[arg(long, default_value_t = false, overrides_with = "always_write")]
+    if_changed: bool,
+
+    /// Write the permissions even when unchanged, whatever the configuration
+    /// file says
+    #[arg(long, default_value_t = false, overrides_with = "if_changed")]
+    always_write: bool,
+
+    /// Lowercase the permissions, for well-known tables whose values all are
+    #[arg(long, default_value_t = false)]
+    normalize_case: bool,
+
+    /// Write the permissions exactly as given, repeats included
+    #[arg(long, default_value_t = false, conflicts_with = "normalize_case")]
+    verbatim: bool,
+
+    /// Add the permissions to the current ones instead of replacing them
+    #[arg(long, default_value_t = false, conflicts_with = "remove")]
+    append: bool,
+
+    /// Remove the permissions from the current ones instead of replacing them
+    #[arg(long, default_value_t = false)]
+    remove: bool,
+
+    /// Write --append and --remove results even when another client changed
+    /// the permissions after they were read
+    #[arg(long, default_value_t = false)]
+    no_retry: bool,
+
+    /// Also set the associated data of the resources to the JSON in this
+    /// file, either as `lookup --format json` prints it or as plain JSON
+    #[arg(long, value_name = "PATH")]
+    data_file: Option<String>,
+
+    #[command(flatten)]
+    diff: ShowDiffArgs,
+
+    #[command(flatten)]
+    verify: VerifyArgs,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource ID (omitted with --id), the application ID (omitted with --app) and the permissions to set
+    #[arg(value_names = ["ID", "APP", "PERMISSIONS"])]
+    targets: Vec<String>,
+}
+
+impl SetArgs {
+    fn strict(&self) -> bool {
+        (self.strict || config::get().strict) && !self.no_strict
+    }
+
+    fn if_changed(&self) -> bool {
+        (self.if_changed || config::get().if_changed) && !self.always_write
+    }
+
+    /// Split the positionals into the resource IDs, the applications and the permissions.
+    fn resolve(&self) -> Option<(Vec<&str>, Vec<&str>, &[String])> {
+        fn take<'a>(flags: &'a [String], rest: &mut &'a [String]) -> Option<Vec<&'a str>> {
+            if !flags.is_empty() {
+                return Some(flags.iter().map(String::as_str).collect());
+            }
+            let (first, remaining) = rest.split_first()?;
+            *rest = remaining;
+            Some(vec![first.as_str()])
+        }
+
+        let mut rest = &self.targets[..];
+        let ids = take(&self.ids, &mut rest)?;
+        let apps = take(&self.apps, &mut rest)?;
+        Some((ids, apps, rest))
+    }
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// Compare the resource against a JSON snapshot taken with `lookup --format json`
+    #[arg(long, conflicts_with_all = ["app_a", "app_b"])]
+    snapshot: Option<String>,
+
+    /// Exit successfully even if the snapshot differs
+    #[arg(long, default_value_t = false, requires = "snapshot")]
+    exit_zero: bool,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource ID to compare
+    id: String,
+
+    /// The first application
+    #[arg(required_unless_present = "snapshot")]
+    app_a: Option<String>,
+
+    /// The second application
+    #[arg(required_unless_present = "snapshot")]
+    app_b: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ExplainArgs {
+    /// Only describe this table
+    table: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ExportCsvArgs {
+    /// The name of the table to export
+    table: String,
+
+    /// Where to write the CSV, `-` for stdout
+    file: String,
+}
+
+#[derive(Args, Debug)]
+struct ImportCsvArgs {
+    /// Whether to create the table if it does not exist
+    #[arg(short, long, default_value_t = false)]
+    create: bool,
+
+    /// The name of the table to import into
+    table: String,
+
+    /// The CSV to read, `-` for stdin; associated data is kept as it is
+    file: String,
+}
+
+#[derive(Args, Debug)]
+struct MatrixArgs {
+    /// Only print the columns of these applications, in this order
+    #[arg(long, value_delimiter = ',')]
+    apps: Vec<String>,
+
+    /// Print yes, no and ask as glyphs
+    #[arg(long, default_value_t = false)]
+    compact: bool,
+
+    /// Print comma-separated values instead of a table
+    #[arg(long, default_value_t = false)]
+    csv: bool,
+
+    /// The name of the table to use
+    table: String,
+}
+
+#[derive(Args, Debug)]
+struct MergeArgs {
+    /// Delete the source application's entry afterwards
+    #[arg(long, default_value_t = false)]
+    remove_src: bool,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource ID to modify
+    id: String,
+
+    /// The application whose permissions are merged
+    src_app: String,
+
+    /// The application receiving the union of both permission lists
+    dst_app: String,
+}
+
+#[derive(Args, Debug)]
+struct MigrateArgs {
+    /// Only print the planned rewrites
+    #[arg(short = 'n', long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Replace conflicting entries of the new application IDs
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+
+    /// Additional tables to scan besides the well-known ones
+    #[arg(long = "table", value_delimiter = ',')]
+    tables: Vec<String>,
+
+    /// TOML file mapping old application IDs to new ones
+    mapping: String,
+}
+
+#[derive(Args, Debug)]
+struct MigrateAppArgs {
+    /// Only print the planned rewrites
+    #[arg(short = 'n', long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Replace conflicting entries of the new application ID
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+
+    /// The tables to scan, defaults to the well-known portal tables
+    #[arg(long = "table", visible_alias = "tables", value_delimiter = ',')]
+    tables: Vec<String>,
+
+    /// The current application ID
+    old_app: String,
+
+    /// The new application ID
+    new_app: String,
+}
+
+#[derive(Args, Debug)]
+struct MoveArgs {
+    /// Replace an existing resource with the new ID
+    #[arg(short, long, default_value_t = false)]
+    force: bool,
+
+    /// Copy the resource instead of moving it
+    #[arg(long, default_value_t = false)]
+    keep_source: bool,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource ID to move
+    old_id: String,
+
+    /// The new resource ID
+    new_id: String,
+}
+
+#[derive(Args, Debug)]
+struct PurgeAppArgs {
+    /// Purge the application from every well-known table
+    #[arg(long, default_value_t = false)]
+    all_tables: bool,
+
+    /// Only print the resources that would be cleaned
+    #[arg(short = 'n', long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Do not ask for confirmation
+    #[arg(short, long, default_value_t = false)]
+    yes: bool,
+
+    /// The name of the table (omitted with --all-tables) and the application ID
+    #[arg(value_names = ["TABLE", "APP"], num_args = 1..=2, required = true)]
+    targets: Vec<String>,
+}
+
+impl PurgeAppArgs {
+    /// Split the positionals into the tables to scan and the application ID.
+    fn resolve(&self) -> Option<(Vec<String>, &str)> {
+        match (self.all_tables, self.targets.as_slice()) {
+            (false, [table, app]) => Some((vec![table.clone()], app)),
+            (true, [app]) => Some((known_tables(), app)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct RenameAppArgs {
+    /// Replace an existing entry of the new application ID
+    #[arg(short, long, default_value_t = false)]
+    force: bool,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource ID to modify
+    id: String,
+
+    /// The current application ID
+    old_app: String,
+
+    /// The new application ID
+    new_app: String,
+}
+
+impl RenameAppArgs {
+    /// Whether both application IDs are the same, which would write the
+    /// entry back and then delete it.
+    fn onto_itself(&self) -> bool {
+        self.old_app == self.new_app
+    }
+}
+
+#[derive(Args, Debug)]
+struct RestoreArgs {
+    /// Whether to create the table if it does not exist
+    #[arg(short, long, default_value_t = false)]
+    create: bool,
+
+    /// Restore into this table instead of the one the snapshot was taken of
+    #[arg(long)]
+    table: Option<String>,
+
+    /// A snapshot written by `snapshot`
+    file: String,
+
+    /// Only restore these resources, every one of the snapshot by default
+    ids: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct RevokeArgs {
+    /// Delete the application's entry instead of writing an empty permission list
+    #[arg(long, default_value_t = false)]
+    delete_empty: bool,
+
+    /// Fail if any of the permissions is not present
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+
+    /// Write even when another client changed the permissions after they
+    /// were read
+    #[arg(long, default_value_t = false)]
+    no_retry: bool,
+
+    /// Do nothing, successfully, when the application has no entry
+    #[arg(long, default_value_t = false)]
+    if_exists: bool,
+
+    #[command(flatten)]
+    diff: ShowDiffArgs,
+
+    #[command(flatten)]
+    verify: VerifyArgs,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource ID to modify
+    id: String,
+
+    /// The application ID to modify
+    app: String,
+
+    /// The permissions to revoke
+    #[arg(required = true)]
+    permissions: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct BackgroundArgs {
+    /// What to do
+    #[arg(value_enum)]
+    action: BackgroundAction,
+
+    /// Name of the application, not needed by list
+    app: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct BenchLookupArgs {
+    /// How many lookups to make
+    #[arg(short = 'n', long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+    iterations: u32,
+
+    /// The name of the table to use
+    table: String,
+
+    /// The resource ID to look up
+    id: String,
+}
+
+#[derive(Args, Debug)]
+struct DevicesArgs {
+    /// What to do
+    #[arg(value_enum)]
+    action: DeviceAction,
+
+    /// The device resource to act on
+    #[arg(value_enum)]
+    device: Device,
+
+    /// Name of the application, status without it prints every application
+    #[arg(required_if_eq_any([("action", "allow"), ("action", "deny"), ("action", "ask")]))]
+    app: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct InhibitArgs {
+    /// What to do
+    #[arg(value_enum)]
+    action: InhibitAction,
+
+    /// Name of the application, not needed by list
+    #[arg(required_if_eq_any([("action", "allow"), ("action", "deny"), ("action", "status")]))]
+    app: Option<String>,
+
+    /// Only allow or deny these, keeping the others as they are
+    #[arg(long = "flag", value_enum, value_delimiter = ',')]
+    flags: Vec<InhibitFlag>,
+}
+
+#[derive(Args, Debug)]
+struct LocationArgs {
+    /// What to do
+    #[arg(value_enum)]
+    action: LocationAction,
+
+    /// Name of the application
+    app: String,
+
+    /// The accuracy to allow, the previous one or exact by default
+    #[arg(long, value_enum)]
+    accuracy: Option<LocationAccuracy>,
+}
+
+#[derive(Args, Debug)]
+struct NotificationsArgs {
+    /// What to do
+    #[arg(value_enum)]
+    action: NotificationAction,
+
+    /// Name of the application
+    app: String,
+}
+
+#[derive(Args, Debug)]
+struct ScreenshotArgs {
+    /// What to do
+    #[arg(value_enum)]
+    action: ScreenshotAction,
+
+    /// Name of the application, not needed by list
+    #[arg(required_if_eq_any([
+        ("action", "allow"),
+        ("action", "deny"),
+        ("action", "reset"),
+        ("action", "status"),
+    ]))]
+    app: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct WallpaperArgs {
+    /// What to do
+    #[arg(value_enum)]
+    action: WallpaperAction,
+
+    /// Name of the application, not needed by list
+    #[arg(required_if_eq_any([("action", "allow"), ("action", "deny"), ("action", "status")]))]
+    app: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ShowArgs {
+    /// Another table to scan besides the well-known portal tables, may be repeated
+    #[arg(long = "table", value_name = "TABLE", value_delimiter = ',')]
+    tables: Vec<String>,
+
+    /// Also print the associated data of every resource
+    #[arg(long, default_value_t = false)]
+    with_data: bool,
+
+    /// Also list the tables the application has no entry in
+    #[arg(long, default_value_t = false)]
+    all: bool,
+
+    /// Name of the application
+    app: String,
+}
+
+#[derive(Args, Debug)]
+struct SnapshotArgs {
+    /// Capture every well-known table there is, each into its own file
+    #[arg(long, default_value_t = false)]
+    all_tables: bool,
+
+    /// How many tables to capture at once with --all-tables
+    #[arg(short, long, default_value_t = 4, requires = "all_tables",
+          value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: u32,
+
+    #[command(flatten)]
+    filter: IdFilterArgs,
+
+    #[command(flatten)]
+    matching: MatchArgs,
+
+    /// The name of the table to capture (omitted with --all-tables) and where
+    /// to write the snapshot, defaults to `<table>-<timestamp>.json`. With
+    /// --all-tables, the directory for the files
+    #[arg(value_names = ["TABLE", "FILE"], num_args = 0..=2, required_unless_present = "all_tables")]
+    targets: Vec<String>,
+}
+
+impl SnapshotArgs {
+    /// Split the positionals into the tables to capture and the destination.
+    fn resolve(&self) -> Option<(Vec<String>, Option<&str>)> {
+        match (self.all_tables, self.targets.as_slice()) {
+            (false, [table]) => Some((vec![table.clone()], None)),
+            (false, [table, file]) => Some((vec![table.clone()], Some(file))),
+            (true, []) => Some((known_tables(), None)),
+            (true, [dir]) => Some((known_tables(), Some(dir))),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct StatsArgs {
+    /// Summarize every well-known table, the default without tables
+    #[arg(long, default_value_t = false, conflicts_with = "tables")]
+    all_tables: bool,
+
+    /// How many of the resources with the most applications to print
+    #[arg(long, default_value_t = 5)]
+    top: usize,
+
+    /// The tables to summarize, defaults to the well-known portal tables
+    tables: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct TablesArgs {
+    /// Check which of the tables exist and how many resources they hold
+    #[arg(long, default_value_t = false)]
+    probe: bool,
+}
+
+#[derive(Args, Debug)]
+struct WatchArgs {
+    /// A shell command to run for every change, with the change in
+    /// XDG_PERM_TABLE, XDG_PERM_ID, XDG_PERM_APP, XDG_PERM_PERMISSIONS
+    /// and XDG_PERM_DELETED
+    #[arg(long, value_name = "COMMAND")]
+    exec: Option<String>,
+
+    /// How many times in a row to try reconnecting after losing the bus,
+    /// 0 to stop instead
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    max_reconnect: u32,
+
+    /// Only follow this table
+    table: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct WhereArgs {
+    /// The tables to scan, defaults to the well-known portal tables
+    #[arg(long = "table", visible_alias = "tables", value_delimiter = ',')]
+    tables: Vec<String>,
+
+    /// Name of the application
+    app: String,
+}
+
+// custom DBus type
+
+type LookupResponse = (HashMap<String, Vec<String>>, OwnedValue);
+
+#[proxy(
+    interface = "org.freedesktop.impl.portal.PermissionStore",
+    default_service = "org.freedesktop.impl.portal.PermissionStore",
+    default_path = "/org/freedesktop/impl/portal/PermissionStore",
+    gen_async = true
+)]
+trait PermissionStore {
+    #[zbus(property(emits_changed_signal = "const"), name = "version")]
+    fn version(&self) -> zbus::Result<u32>;
+
+    fn delete(&self, table: &str, id: &str) -> zbus::Result<()>;
+    fn delete_permission(&self, table: &str, id: &str, app: &str) -> zbus::Result<()>;
+    fn get_permission(&self, table: &str, id: &str, app: &str) -> zbus::Result<Vec<String>>;
+    fn list(&self, table: &str) -> zbus::Result<Vec<String>>;
+    fn lookup(&self, table: &str, id: &str) -> zbus::Result<LookupResponse>;
+    fn set_permission(
+        &self,
+        table: &str,
+        create: bool,
+        id: &str,
+        app: &str,
+        permissions: &[String],
+    ) -> zbus::Result<()>;
+    fn set(
+        &self,
+        table: &str,
+        create: bool,
+        id: &str,
+        app_permissions: &HashMap<String, Vec<String>>,
+        data: &Value<'_>,
+    ) -> zbus::Result<()>;
+    fn set_value(&self, table: &str, create: bool, id: &str, data: &Value<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn changed(
+        &self,
+        table: &str,
+        id: &str,
+        deleted: bool,
+        data: Value<'_>,
+        permissions: HashMap<String, Vec<String>>,
+    ) -> zbus::Result<()>;
+}
+
+// main impl
+
+/// Set from `--quiet` for the duration of a command, see `status!`.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Set from `--max-width` for the duration of a command, 0 when unset, see `new_table`.
+static MAX_WIDTH: AtomicU16 = AtomicU16::new(0);
+
+/// Set from `--no-header` for the duration of a command.
+static NO_HEADER: AtomicBool = AtomicBool::new(false);
+
+/// Set from `--style` for the duration of a command, see `new_table`.
+static STYLE: Mutex<TableStyle> = Mutex::new(TableStyle::Boxed);
+
+/// Set from `--color` for the duration of a command, see `new_table`.
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Set from `--allow-invalid-app-id` for the duration of a command, see
+/// `check_app_ids`.
+static ALLOW_INVALID_APP_ID: AtomicBool = AtomicBool::new(false);
+
+/// Set from `--timing` for the duration of a command, see `store::timed`.
+static TIMING: AtomicBool = AtomicBool::new(false);
+
+/// Set from `--format markdown` for the duration of a command, see `new_table`.
+static MARKDOWN: AtomicBool = AtomicBool::new(false);
+
+/// Set from `-v` for the duration of a command, see `init_tracing`.
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+/// Log our own spans and events to stderr at a level picked by `VERBOSITY`,
+/// which is read on every event so each repl line can choose its own.
+fn init_tracing() {
+    let filter = tracing_subscriber::filter::filter_fn(|metadata| {
+        let level = match VERBOSITY.load(Ordering::Relaxed) {
+            0 => return false,
+            1 => Level::INFO,
+            _ => Level::DEBUG,
+        };
+        *metadata.level() <= level && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    });
+    tracing_subscriber::registry()
+        .with(
+            fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_span_events(FmtSpan::NEW)
+                .with_filter(filter),
+        )
+        .init();
+}
+
+const PERMISSION_STORE_SPEC_VER: u32 = 2;
+
+const NOT_FOUND_ERROR: &str = "org.freedesktop.portal.Error.NotFound";
+
+const SERVICE_UNKNOWN_ERROR: &str = "org.freedesktop.DBus.Error.ServiceUnknown";
+
+const SERVICE_HINT: &str = "hint: the permission store is provided by xdg-permission-store, which is started by xdg-desktop-portal.
+Make sure xdg-desktop-portal is installed and start it with `systemctl --user start xdg-desktop-portal`.";
+
+/// Exit codes of the check subcommand, chosen so scripts can branch on them.
+const CHECK_ERROR: u8 = 2;
+const CHECK_NO_ENTRY: u8 = 3;
+
+/// Exit code of lookup when none of the `--app` applications has an entry,
+/// the same as check uses.
+const LOOKUP_NO_APP: u8 = CHECK_NO_ENTRY;
+
+/// Exit code of get when the application has no entry at all, as opposed to
+/// an entry without permissions.
+const GET_NO_ENTRY: u8 = CHECK_NO_ENTRY;
+
+/// Exit code of set and revoke when `--verify` reads back other permissions
+/// than were written.
+const VERIFY_MISMATCH: u8 = 4;
+
+/// Upper bound on in-flight lookups when scanning a whole table.
+const LOOKUP_CONCURRENCY: usize = 16;
+
+/// Upper bound on in-flight deletes when clearing a table.
+const DELETE_CONCURRENCY: usize = 8;
+
+/// A table that wraps its cells to `--max-width` or the terminal width.
+///
+/// Piped output is never wrapped unless a width is given, so it stays stable.
+/// Markdown tables are never wrapped either, wrapping would split their rows.
+fn new_table() -> Table {
+    table_of_width(MAX_WIDTH.load(Ordering::Relaxed))
+}
+
+/// `new_table` with a `--max-width` of `max_width`, 0 when unset.
+fn table_of_width(max_width: u16) -> Table {
+    let mut table = Table::new();
+    if COLOR.load(Ordering::Relaxed) {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+    if MARKDOWN.load(Ordering::Relaxed) {
+        table.load_preset(ASCII_MARKDOWN);
+        return table;
+    }
+    match *STYLE.lock().unwrap_or_else(PoisonError::into_inner) {
+        TableStyle::Boxed => {}
+        TableStyle::Plain => {
+            table.load_preset(NOTHING);
+        }
+        TableStyle::Utf8 => {
+            table.load_preset(UTF8_FULL);
+        }
+        TableStyle::Rounded => {
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS);
+        }
+    }
+    match max_width {
+        0 if std::io::stdout().is_terminal() => {
+            table.set_content_arrangement(ContentArrangement::Dynamic);
+        }
+        0 => {}
+        width => {
+            table
+                .set_content_arrangement(ContentArrangement::Dynamic)
+                .set_width(width);
+        }
+    }
+    table
+}
+
+/// The color of a cell holding only `permissions`, for the single values
+/// portals give a meaning: yes is green, no red and ask yellow.
+fn permission_color(permissions: &[String]) -> Option<Color> {
+    match permissions {
+        [value] => match value.as_str() {
+            "yes" => Some(Color::Green),
+            "no" => Some(Color::Red),
+            "ask" => Some(Color::Yellow),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A cell listing `permissions`, colored by `permission_color`; `new_table`
+/// drops the color unless `--color` allows it.
+fn permissions_cell(permissions: &[String]) -> Cell {
+    let cell = Cell::new(cell_text(&permissions.join(",")));
+    match permission_color(permissions) {
+        Some(color) => cell.fg(color),
+        None => cell,
+    }
+}
+
+/// Escape the characters that would end a Markdown table cell early.
+fn cell_text(text: &str) -> Cow<'_, str> {
+    if MARKDOWN.load(Ordering::Relaxed) && text.contains('|') {
+        Cow::Owned(text.replace('|', "\\|"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+fn is_not_found(error: &zbus::Error) -> bool {
+    matches!(error, zbus::Error::MethodError(name, _, _) if name.as_str() == NOT_FOUND_ERROR)
+}
+
+/// After `id` was not found in `table`, tell whether the table or the
+/// resource is missing, and what may have been meant.
+async fn hint_not_found(proxy: &Store<'_>, table: &str, id: &str) {
+    match proxy.list(table).await {
+        Ok(ids) => match tables::nearest(id, ids.iter().map(String::as_str)).as_slice() {
+            [] => eprintln!("hint: {table} has no resource {id}, see `list {table}`"),
+            near => eprintln!(
+                "hint: {table} has no resource {id}, did you mean {}?",
+                near.join(", ")
+            ),
+        },
+        Err(e) if is_not_found(&e) => {
+            let known = tables::KNOWN_TABLES.iter().map(|known| known.name);
+            match tables::nearest(table, known).first() {
+                Some(known) => eprintln!("hint: there is no table {table}, did you mean {known}?"),
+                None => eprintln!(
+                    "hint: there is no table {table}, `set --create` creates it and `tables` \
+                     lists the well-known ones"
+                ),
+            }
+        }
+        // the first error was reported already
+        Err(_) => {}
+    }
+}
+
+/// Nothing on the bus owns, or can be activated for, the permission store name.
+fn is_service_unknown(error: &zbus::Error) -> bool {
+    matches!(error, zbus::Error::MethodError(name, _, _) if name.as_str() == SERVICE_UNKNOWN_ERROR)
+        || matches!(error, zbus::Error::FDO(e) if matches!(**e, zbus::fdo::Error::ServiceUnknown(_)))
+}
+
+/// Whether `app` has an entry, even an empty one, for `id` in `table`.
+async fn has_entry(proxy: &Store<'_>, table: &str, id: &str, app: &str) -> zbus::Result<bool> {
+    match proxy.lookup(table, id).await {
+        Ok((apps, _)) => Ok(apps.contains_key(app)),
+        Err(e) if is_not_found(&e) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+async fn check_permission(proxy: &Store<'_>, args: &CheckArgs) -> ExitCode {
+    let permissions = match proxy.lookup(&args.table, &args.id).await {
+        Ok((mut apps, _)) => apps.remove(&args.app),
+        Err(e) if is_not_found(&e) => None,
+        Err(e) => {
+            fail!(e, "failed to lookup permissions: {e}");
+            return ExitCode::from(CHECK_ERROR);
+        }
+    };
+
+    let Some(permissions) = permissions else {
+        if args.print_value {
+            println!("{} has no entry for {}", args.app, args.id);
+        }
+        return ExitCode::from(CHECK_NO_ENTRY);
+    };
+
+    if args.print_value {
+        println!("{}: {}", args.app, permissions.join(","));
+    }
+
+    let found = args
+        .permission
+        .iter()
+        .chain(args.values.iter())
+        .any(|value| permissions.contains(value));
+    if found {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Expand `--glob` resource IDs into the matching IDs of `table`, listing
+/// them on stderr. `None` once a failure, or the lack of any match, is reported.
+async fn expand_ids(
+    proxy: &Store<'_>,
+    table: &str,
+    patterns: &[String],
+    matching: &MatchArgs,
+) -> Option<Vec<String>> {
+    let globs: Result<Vec<_>, _> = patterns
+        .iter()
+        .map(|pattern| matching.glob(pattern))
+        .collect();
+    let globs = globs.inspect_err(|e| fail!(e, "{e}")).ok()?;
+    let mut ids = match proxy.list(table).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            fail!(e, "failed to list permissions: {e}");
+            return None;
+        }
+    };
+    ids.retain(|id| globs.iter().any(|glob| glob.matches(id)));
+    ids.sort();
+
+    if ids.is_empty() {
+        fail!(
+            ErrorKind::NotFound,
+            "no resource of {table} matches {}",
+            patterns.join(" ")
+        );
+        return None;
+    }
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("{} resource(s) matched: {}", ids.len(), ids.join(" "));
+    }
+    Some(ids)
+}
+
+async fn delete_permission(proxy: &Store<'_>, args: &DeleteArgs, format: Format) -> ExitCode {
+    let mut changes = ChangeLog::new(proxy, &args.table, &args.diff);
+    let (ids, apps) = match args.resolve() {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            fail!(e, "{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let names = apps.iter().filter(|app| !app.contains(['*', '?', '[']));
+    if !check_app_ids(names.copied()) {
+        return ExitCode::FAILURE;
+    }
+    let expanded;
+    let ids = if args.glob {
+        let Some(matched) = expand_ids(proxy, &args.table, ids, &args.matching).await else {
+            return ExitCode::FAILURE;
+        };
+        expanded = matched;
+        // whole resources are confirmed below anyway
+        let count = expanded.len();
+        if !apps.is_empty()
+            && count > 1
+            && !args.yes
+            && !confirm(&format!("Delete from {count} resource(s)?"))
+        {
+            fail!(ErrorKind::Aborted, "aborted");
+            return ExitCode::FAILURE;
+        }
+        &expanded[..]
+    } else {
+        ids
+    };
+    if apps.is_empty() {
+        let code = delete_resources(
+            proxy,
+            &mut changes,
+            &args.table,
+            ids,
+            args.yes,
+            args.if_exists,
+        )
+        .await;
+        changes.print(format);
+        return code;
+    }
+
+    // application IDs never contain glob characters, so no opt-in is needed
+    let patterns: Result<Vec<_>, _> = apps
+        .iter()
+        .map(|app| {
+            if app.contains(['*', '?', '[']) {
+                args.matching.glob(app).map(|glob| (*app, Some(glob)))
+            } else {
+                Ok((*app, None))
+            }
+        })
+        .collect();
+    let Ok(patterns) = patterns.inspect_err(|e| fail!(e, "{e}")) else {
+        return ExitCode::FAILURE;
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    for id in ids {
+        let present = match proxy.lookup(&args.table, id).await {
+            Ok((present, _)) => present,
+            Err(e) if is_not_found(&e) => HashMap::new(),
+            Err(e) => {
+                fail!(e, "{id}: failed to lookup permissions: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        let mut targets: Vec<String> = Vec::with_capacity(patterns.len());
+        for (app, glob) in &patterns {
+            let mut matched: Vec<&String> = match glob {
+                Some(glob) => present.keys().filter(|app| glob.matches(app)).collect(),
+                None => present
+                    .get_key_value(*app)
+                    .map(|(app, _)| app)
+                    .into_iter()
+                    .collect(),
+            };
+            matched.sort();
+            if matched.is_empty() {
+                if args.if_exists {
+                    status!("{id} {app}: nothing to delete");
+                } else {
+                    status!("{id} {app}: not present");
+                }
+                if args.strict {
+                    code = ExitCode::FAILURE;
+                }
+                continue;
+            }
+            if matched.len() > 1 && !args.force {
+                fail!(
+                    ErrorKind::InvalidInput,
+                    "{id} {app}: matches {} applications, pass --force to delete them all",
+                    matched.len()
+                );
+                code = ExitCode::FAILURE;
+                continue;
+            }
+            for app in matched {
+                if !targets.contains(app) {
+                    targets.push(app.clone());
+                }
+            }
+        }
+
+        let mut deleted = Vec::with_capacity(targets.len());
+        for app in &targets {
+            match proxy.delete_permission(&args.table, id, app).await {
+                Ok(_) => {
+                    status!("{id} {app}: permissions deleted successfully");
+                    deleted.push(app.as_str());
+                }
+                // gone since the lookup
+                Err(e) if args.if_exists && is_not_found(&e) => {
+                    status!("{id} {app}: nothing to delete");
+                }
+                Err(e) => {
+                    fail!(e, "{id} {app}: failed to delete permissions: {e}");
+                    code = ExitCode::FAILURE;
+                }
+            }
+        }
+
+        // the lookup above already is the state before the deletes
+        let before = args.diff.enabled().then_some(present);
+        if !deleted.is_empty() {
+            changes.after(id, Some(&deleted), before).await;
+        }
+    }
+
+    changes.print(format);
+    code
+}
+
+async fn delete_resources(
+    proxy: &Store<'_>,
+    changes: &mut ChangeLog<'_>,
+    table: &str,
+    ids: &[String],
+    yes: bool,
+    if_exists: bool,
+) -> ExitCode {
+    if !yes && !confirm(&format!("Delete every entry of {} resource(s)?", ids.len())) {
+        fail!(ErrorKind::Aborted, "aborted");
+        return ExitCode::FAILURE;
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    for id in ids {
+        let before = changes.before(id).await;
+        match proxy.delete(table, id).await {
+            Ok(_) => {
+                status!("{id}: permissions deleted successfully");
+                changes.after(id, None, before).await;
+            }
+            Err(e) if if_exists && is_not_found(&e) => status!("{id}: nothing to delete"),
+            Err(e) => {
+                fail!(e, "{id}: failed to delete permissions: {e}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    code
+}
+
+async fn clear_table(proxy: &Store<'_>, args: &ClearTableArgs) -> ExitCode {
+    let filter = match args.filter.compile(&args.matching) {
+        Ok(filter) => filter,
+        Err(e) => {
+            fail!(e, "{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut ids = match proxy.list(&args.table).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            fail!(e, "failed to list permissions: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    filter.retain(&mut ids);
+
+    if args.dry_run {
+        for id in &ids {
+            println!("{id}");
+        }
+        println!(
+            "{} resource(s) would be deleted from {}",
+            ids.len(),
+            args.table
+        );
+        return ExitCode::SUCCESS;
+    }
+
+    if ids.is_empty() {
+        status!("nothing to delete in {}", args.table);
+        return ExitCode::SUCCESS;
+    }
+
+    status!(
+        "{} resource(s) will be deleted from {}",
+        ids.len(),
+        args.table
+    );
+    if !args.yes && !confirm("Continue?") {
+        fail!(ErrorKind::Aborted, "aborted");
+        return ExitCode::FAILURE;
+    }
+
+    let (mut deleted, mut vanished, mut failed) = (0, 0, 0);
+    let mut results = stream::iter(&ids)
+        .map(|id| async move { (id, proxy.delete(&args.table, id).await) })
+        .buffer_unordered(DELETE_CONCURRENCY);
+    while let Some((id, result)) = results.next().await {
+        match result {
+            Ok(_) => deleted += 1,
+            // deleted concurrently, which is what we wanted anyway
+            Err(e) if is_not_found(&e) => vanished += 1,
+            Err(e) => {
+                fail!(e, "{id}: failed to delete permissions: {e}");
+                failed += 1;
+            }
+        }
+    }
+
+    status!("{deleted} deleted, {vanished} already gone, {failed} failed");
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// `permissions` with only the first of any repeated value, and the
+/// repeats that were taken out.
+fn dedup_permissions<'a>(
+    permissions: impl IntoIterator<Item = &'a String>,
+) -> (Vec<String>, Vec<String>) {
+    let mut unique: Vec<String> = Vec::new();
+    let mut repeats = Vec::new();
+    for permission in permissions {
+        if unique.contains(permission) {
+            repeats.push(permission.clone());
+        } else {
+            unique.push(permission.clone());
+        }
+    }
+    (unique, repeats)
+}
+
+/// The permissions `set` writes for `requested`, without repeats and, with
+/// `--normalize-case`, lowercase. Kept as given with `--verbatim`.
+fn normalize_permissions(args: &SetArgs, requested: &[String]) -> Vec<String> {
+    if args.verbatim {
+        return requested.to_vec();
+    }
+    let lowercase = args.normalize_case && tables::is_lowercase(&args.table);
+    if args.normalize_case && !lowercase {
+        eprintln!(
+            "warning: {} has no known lowercase values, keeping the case of the permissions",
+            args.table
+        );
+    }
+    let requested: Vec<String> = if lowercase {
+        requested.iter().map(|p| p.to_lowercase()).collect()
+    } else {
+        requested.to_vec()
+    };
+
+    let (unique, repeats) = dedup_permissions(&requested);
+    if !repeats.is_empty() {
+        status!("Ignoring repeated permissions: {}", repeats.join(", "));
+    }
+    unique
+}
+
+/// Add `requested` to `current`, or take it away with `remove`.
+///
+/// The order of `current` is kept and duplicates are only ever added once.
+fn merge_permissions(current: &[String], requested: &[String], remove: bool) -> Vec<String> {
+    if remove {
+        return current
+            .iter()
+            .filter(|permission| !requested.contains(permission))
+            .cloned()
+            .collect();
+    }
+    dedup_permissions(current.iter().chain(requested)).0
+}
+
+/// The permissions of `app` on `id` after `set --append` or `--remove` of
+/// `requested`, `None` once the failure is reported.
+async fn merged_permissions(
+    proxy: &Store<'_>,
+    args: &SetArgs,
+    id: &str,
+    app: &str,
+    requested: &[String],
+) -> Option<Vec<String>> {
+    match proxy.get_permission(&args.table, id, app).await {
+        Ok(current) => Some(merge_permissions(&current, requested, args.remove)),
+        // a brand new entry, which --create turns into a plain set
+        Err(e) if is_not_found(&e) => Some(merge_permissions(&[], requested, args.remove)),
+        Err(e) => {
+            fail!(e, "{id} {app}: failed to get permissions: {e}");
+            None
+        }
+    }
+}
+
+/// Check that replacing the permissions of `app` with `permissions` drops
+/// none of the current ones, or that the user wants that anyway.
+async fn replace_permissions(
+    proxy: &Store<'_>,
+    args: &SetArgs,
+    id: &str,
+    app: &str,
+    permissions: &[String],
+) -> Result<(), ()> {
+    if args.force {
+        return Ok(());
+    }
+    let current = match proxy.get_permission(&args.table, id, app).await {
+        Ok(current) => current,
+        // nothing to drop in a brand new entry
+        Err(e) if is_not_found(&e) => return Ok(()),
+        Err(e) => {
+            fail!(e, "{id} {app}: failed to get permissions: {e}");
+            return Err(());
+        }
+    };
+    if current
+        .iter()
+        .all(|permission| permissions.contains(permission))
+    {
+        return Ok(());
+    }
+
+    eprintln!(
+        "warning: {id} {app}: replacing [{}] with [{}]",
+        current.join(", "),
+        permissions.join(", ")
+    );
+    if confirm("Drop the permissions not in the new list?") {
+        Ok(())
+    } else {
+        fail!(
+            ErrorKind::Aborted,
+            "{id} {app}: skipped, pass --force to drop them without asking"
+        );
+        Err(())
+    }
+}
+
+/// Whether `set --if-changed` wrote an application.
+#[derive(Serialize, Debug)]
+struct SetOutcome<'a> {
+    id: &'a str,
+    app: &'a str,
+    changed: bool,
+}
+
+/// What `set --if-changed --format json` prints.
+#[derive(Serialize, Debug)]
+struct SetReport<'a> {
+    outcomes: Vec<SetOutcome<'a>>,
+    /// `None` without --show-diff
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changes: Option<&'a [Change]>,
+}
+
+/// Whether `app` already holds exactly `permissions` on `id`.
+async fn is_unchanged(
+    proxy: &Store<'_>,
+    table: &str,
+    id: &str,
+    app: &str,
+    permissions: &[String],
+) -> zbus::Result<bool> {
+    match proxy.lookup(table, id).await {
+        Ok((mut apps, _)) => Ok(apps.remove(app).as_deref() == Some(permissions)),
+        // a new resource, which the write creates
+        Err(e) if is_not_found(&e) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+async fn set_permission(proxy: &Store<'_>, args: &SetArgs, format: Format) -> ExitCode {
+    let Some((ids, apps, permissions)) = args.resolve() else {
+        fail!(
+            ErrorKind::InvalidInput,
+            "expected a resource ID and an application ID"
+        );
+        return ExitCode::FAILURE;
+    };
+    if !check_app_ids(apps.iter().copied()) {
+        return ExitCode::FAILURE;
+    }
+    // backends read an empty list as denying everything or as no entry
+    if permissions.is_empty() && !args.allow_empty {
+        let e = "no permissions given: pass the permissions to set, --allow-empty to store an \
+                 empty list, or use delete to remove the entry"
+            .to_string();
+        fail!(e, "{e}");
+        return ExitCode::FAILURE;
+    }
+    let strict = args.strict();
+    let normalized = normalize_permissions(args, permissions);
+    let permissions = normalized.as_slice();
+    let unknown = tables::unknown_values(&args.table, permissions);
+    for value in &unknown {
+        let hint = match tables::suggest(&args.table, value) {
+            Some(known) => format!(", did you mean {known}?"),
+            None => String::new(),
+        };
+        let message = format!(
+            "{value} is not a value the portal owning {} understands{hint}",
+            args.table
+        );
+        if strict {
+            fail!(message, "{message}");
+        } else {
+            eprintln!("warning: {message}");
+        }
+    }
+    if strict && !unknown.is_empty() {
+        return ExitCode::FAILURE;
+    }
+    // read it first, so that a bad file changes nothing
+    let data = match &args.data_file {
+        Some(path) => match data::read(path) {
+            Some(data) => Some(data),
+            None => return ExitCode::FAILURE,
+        },
+        None => None,
+    };
+
+    let targets: Vec<(&str, &str)> = ids
+        .iter()
+        .flat_map(|id| apps.iter().map(move |app| (*id, *app)))
+        .collect();
+
+    let mut changes = ChangeLog::new(proxy, &args.table, &args.diff);
+    let mut code = ExitCode::SUCCESS;
+    // with --if-exists or --if-absent, only the resources of the entries
+    // that were left to set get the data
+    let mut written = HashSet::new();
+    let mut outcomes = Vec::new();
+    // entries skipped above never wrote, so never created the table
+    let mut attempted = false;
+    for (id, app) in &targets {
+        if args.if_exists || args.if_absent {
+            match has_entry(proxy, &args.table, id, app).await {
+                Ok(false) if args.if_exists => {
+                    status!("{id} {app}: no entry, skipped");
+                    continue;
+                }
+                Ok(true) if args.if_absent => {
+                    if strict {
+                        fail!(ErrorKind::AlreadyExists, "{id} {app}: already present");
+                        code = ExitCode::FAILURE;
+                        if args.fail_fast {
+                            break;
+                        }
+                    } else {
+                        status!("{id} {app}: already present");
+                    }
+                    continue;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    fail!(e, "{id} {app}: failed to lookup permissions: {e}");
+                    code = ExitCode::FAILURE;
+                    if args.fail_fast {
+                        break;
+                    }
+                    continue;
+                }
+            }
+        }
+        let before = changes.before(id).await;
+        let merging = args.append || args.remove;
+        let mut watch =
+            ConflictWatch::start(proxy, &args.table, id, merging && !args.no_retry).await;
+        let permissions = if merging {
+            match merged_permissions(proxy, args, id, app, permissions).await {
+                Some(merged) => merged,
+                None => {
+                    code = ExitCode::FAILURE;
+                    if args.fail_fast {
+                        break;
+                    }
+                    continue;
+                }
+            }
+        } else {
+            match replace_permissions(proxy, args, id, app, permissions).await {
+                Ok(()) => permissions.to_vec(),
+                Err(()) => {
+                    code = ExitCode::FAILURE;
+                    if args.fail_fast {
+                        break;
+                    }
+                    continue;
+                }
+            }
+        };
+
+        let reread = conflict::reread(&mut watch, permissions, async || {
+            eprintln!("warning: {id} {app}: changed by another client, reading it again");
+            merged_permissions(proxy, args, id, app, &normalized).await
+        });
+        let Some(permissions) = reread.await else {
+            code = ExitCode::FAILURE;
+            if args.fail_fast {
+                break;
+            }
+            continue;
+        };
+
+        if args.if_changed() {
+            match is_unchanged(proxy, &args.table, id, app, &permissions).await {
+                Ok(true) => {
+                    status!("{id} {app}: unchanged");
+                    written.insert(*id);
+                    outcomes.push(SetOutcome {
+                        id,
+                        app,
+                        changed: false,
+                    });
+                    continue;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    fail!(e, "{id} {app}: failed to lookup permissions: {e}");
+                    code = ExitCode::FAILURE;
+                    if args.fail_fast {
+                        break;
+                    }
+                    continue;
+                }
+            }
+        }
+
+        // only the first write needs to create the table
+        let create = args.create && !attempted;
+        attempted = true;
+        let mut result = proxy
+            .set_permission(&args.table, create, id, app, &permissions)
+            .await;
+        if !create && args.create && result.as_ref().is_err_and(is_not_found) {
+            // another client may have deleted the table since, retry once
+            result = proxy
+                .set_permission(&args.table, true, id, app, &permissions)
+                .await;
+        }
+
+        match result {
+            Ok(_) => {
+                let verified = args
+                    .verify
+                    .check(proxy, &args.table, id, app, Some(&permissions))
+                    .await;
+                if verified != Some(true) {
+                    code = match verified {
+                        Some(_) => ExitCode::from(VERIFY_MISMATCH),
+                        None => ExitCode::FAILURE,
+                    };
+                    if args.fail_fast {
+                        break;
+                    }
+                    continue;
+                }
+                if targets.len() == 1 {
+                    status!("Permissions set successfully");
+                } else {
+                    status!("{id} {app}: permissions set successfully");
+                }
+                written.insert(*id);
+                outcomes.push(SetOutcome {
+                    id,
+                    app,
+                    changed: true,
+                });
+                changes.after(id, Some(&[app]), before).await;
+            }
+            Err(e) => {
+                fail!(e, "{id} {app}: failed to set permissions: {e}");
+                code = ExitCode::FAILURE;
+                if args.fail_fast {
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(data) = &data {
+        for id in &ids {
+            if args.fail_fast && code != ExitCode::SUCCESS {
+                break;
+            }
+            if (args.if_exists || args.if_absent) && !written.contains(id) {
+                continue;
+            }
+            match proxy.set_value(&args.table, args.create, id, data).await {
+                Ok(_) => status!("{id}: data set successfully"),
+                Err(e) => {
+                    fail!(e, "{id}: failed to set data: {e}");
+                    code = ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+
+    if args.if_changed() && format == Format::Json {
+        // a single document, with the changes inside for --show-diff
+        print_json(&SetReport {
+            outcomes,
+            changes: changes.recorded(),
+        });
+    } else {
+        changes.print(format);
+    }
+    code
+}
+
+async fn revoke_permission(proxy: &Store<'_>, args: &RevokeArgs, format: Format) -> ExitCode {
+    if !check_app_ids([args.app.as_str()]) {
+        return ExitCode::FAILURE;
+    }
+    if args.if_exists {
+        match has_entry(proxy, &args.table, &args.id, &args.app).await {
+            Ok(true) => {}
+            Ok(false) => {
+                status!(
+                    "{} has no entry for {}, nothing to revoke",
+                    args.app,
+                    args.id
+                );
+                return ExitCode::SUCCESS;
+            }
+            Err(e) => {
+                fail!(e, "failed to lookup permissions: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let mut changes = ChangeLog::new(proxy, &args.table, &args.diff);
+    let before = changes.before(&args.id).await;
+    let mut watch = ConflictWatch::start(proxy, &args.table, &args.id, !args.no_retry).await;
+    let current = match proxy.get_permission(&args.table, &args.id, &args.app).await {
+        Ok(permissions) => permissions,
+        Err(e) => {
+            fail!(e, "failed to get permissions: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let missing: Vec<&String> = args
+        .permissions
+        .iter()
+        .filter(|permission| !current.contains(permission))
+        .collect();
+    for permission in &missing {
+        status!("Permission {permission} is not present");
+    }
+
+    if missing.len() < args.permissions.len() {
+        let reread = conflict::reread(&mut watch, current, async || {
+            eprintln!("warning: changed by another client, reading it again");
+            match proxy.get_permission(&args.table, &args.id, &args.app).await {
+                Ok(permissions) => Some(permissions),
+                Err(e) => {
+                    fail!(e, "failed to get permissions: {e}");
+                    None
+                }
+            }
+        });
+        let Some(current) = reread.await else {
+            return ExitCode::FAILURE;
+        };
+        let remaining: Vec<String> = current
+            .into_iter()
+            .filter(|permission| !args.permissions.contains(permission))
+            .collect();
+
+        let deleted = remaining.is_empty() && args.delete_empty;
+        let result = if deleted {
+            proxy
+                .delete_permission(&args.table, &args.id, &args.app)
+                .await
+        } else {
+            proxy
+                .set_permission(&args.table, false, &args.id, &args.app, &remaining)
+                .await
+        };
+
+        match result {
+            Ok(_) => {
+                let expected = (!deleted).then_some(remaining.as_slice());
+                match args
+                    .verify
+                    .check(proxy, &args.table, &args.id, &args.app, expected)
+                    .await
+                {
+                    Some(true) => {}
+                    Some(false) => return ExitCode::from(VERIFY_MISMATCH),
+                    None => return ExitCode::FAILURE,
+                }
+                status!("Permissions revoked successfully");
+                changes.after(&args.id, Some(&[&args.app]), before).await;
+                changes.print(format);
+            }
+            Err(e) => {
+                fail!(e, "failed to revoke permissions: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if args.strict && !missing.is_empty() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn known_tables() -> Vec<String> {
+    tables::KNOWN_TABLES
+        .iter()
+        .map(|t| t.name.to_string())
+        .collect()
+}
+
+/// Ask the user to confirm a destructive operation on stderr.
+fn confirm(prompt: &str) -> bool {
+    eprint!("{prompt} [y/N] ");
+    let _ = std::io::stderr().flush();
+
+    let mut answer = String::new();
+    match std::io::stdin().lock().read_line(&mut answer) {
+        Ok(_) => matches!(answer.trim(), "y" | "Y" | "yes"),
+        Err(_) => false,
+    }
+}
+
+/// Look up `ids` concurrently, yielding the responses in the order of `ids`.
+fn lookup_stream<'a>(
+    proxy: &'a Store<'_>,
+    table: &'a str,
+    ids: &'a [String],
+) -> impl Stream<Item = (String, zbus::Result<LookupResponse>)> + 'a {
+    stream::iter(ids)
+        .map(move |id| async move { (id.clone(), proxy.lookup(table, id).await) })
+        .buffered(LOOKUP_CONCURRENCY)
+}
+
+#[tracing::instrument(skip_all, fields(table = table, ids = ids.len()))]
+async fn lookup_all(
+    proxy: &Store<'_>,
+    table: &str,
+    ids: &[String],
+) -> Vec<(String, zbus::Result<LookupResponse>)> {
+    lookup_stream(proxy, table, ids).collect().await
+}
+
+/// Compile the `--app` globs of lookup.
+fn app_globs(args: &LookupArgs) -> Option<Vec<Glob>> {
+    let globs: Result<Vec<_>, _> = args
+        .apps
+        .iter()
+        .map(|app| args.matching.glob(app))
+        .collect();
+    globs.inspect_err(|e| fail!(e, "{e}")).ok()
+}
+
+/// Drop the applications matching none of `apps`, every one is kept without
+/// globs. Returns whether any is left.
+fn retain_apps(response: &mut LookupResponse, apps: &[Glob]) -> bool {
+    if !apps.is_empty() {
+        response
+            .0
+            .retain(|app, _| apps.iter().any(|glob| glob.matches(app)));
+    }
+    !response.0.is_empty()
+}
+
+async fn lookup_resource(proxy: &Store<'_>, args: &LookupArgs, format: Format) -> ExitCode {
+    let Some(apps) = app_globs(args) else {
+        return ExitCode::FAILURE;
+    };
+    match proxy.lookup(&args.table, &args.ids[0]).await {
+        Ok(mut result) => {
+            let found = retain_apps(&mut result, &apps);
+            print_lookup_response(&result, args, format);
+            if found || apps.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(LOOKUP_NO_APP)
+            }
+        }
+        Err(e) => {
+            fail!(e, "failed to lookup permissions: {e}");
+            if !args.no_suggest && format != Format::Json && is_not_found(&e) {
+                hint_not_found(proxy, &args.table, &args.ids[0]).await;
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn lookup_resources(proxy: &Store<'_>, args: &LookupArgs, format: Format) -> ExitCode {
+    let Some(apps) = app_globs(args) else {
+        return ExitCode::FAILURE;
+    };
+    let mut responses = Vec::with_capacity(args.ids.len());
+    let (mut missing, mut failed, mut found) = (0, false, false);
+    // concurrent, yet in the order of the arguments
+    for (id, result) in lookup_all(proxy, &args.table, &args.ids).await {
+        match result {
+            Ok(mut response) => {
+                found |= retain_apps(&mut response, &apps);
+                responses.push((id, Some(response)));
+            }
+            Err(e) if is_not_found(&e) => {
+                missing += 1;
+                responses.push((id, None));
+            }
+            Err(e) => {
+                fail!(e, "{id}: failed to lookup permissions: {e}");
+                failed = true;
+            }
+        }
+    }
+
+    if format == Format::Json {
+        let mut json = LookupsJson(Vec::with_capacity(responses.len()));
+        for (id, response) in &responses {
+            // a repeated id would repeat the key
+            if json.0.iter().any(|(seen, _)| seen == id) {
+                continue;
+            }
+            let response = response
+                .as_ref()
+                .map(|response| LookupJson::new(response, args.counts));
+            json.0.push((id, response));
+        }
+        print_json(&json);
+    } else {
+        for (i, (id, response)) in responses.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("== {id} ==");
+            match response {
+                Some(response) => print_lookup_response(response, args, format),
+                None => println!("(missing)"),
+            }
+        }
+    }
+
+    let all_missing = missing == args.ids.len();
+    if failed || all_missing || (args.strict && missing > 0) {
+        ExitCode::FAILURE
+    } else if !found && !apps.is_empty() {
+        ExitCode::from(LOOKUP_NO_APP)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+async fn list_resources(proxy: &Store<'_>, args: &ListArgs, format: Format) -> ExitCode {
+    let filter = match args.filter.compile(&args.matching) {
+        Ok(filter) => filter,
+        Err(e) => {
+            fail!(e, "{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let sort = args.sort();
+    let details = args.details || args.with_data;
+    if sort == ListSort::Permissions && !details {
+        fail!(
+            ErrorKind::InvalidInput,
+            "--sort permissions needs --details or --with-data"
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let tables = args.tables();
+    let several = tables.len() > 1;
+    let mut code = ExitCode::SUCCESS;
+    let mut listings = Vec::with_capacity(tables.len());
+    for table in tables {
+        let mut ids = match proxy.list(table).await {
+            Ok(ids) => ids,
+            // one missing table should not hide the others
+            Err(e) if several && is_not_found(&e) => {
+                eprintln!("{table}: no such table, listed as empty");
+                Vec::new()
+            }
+            Err(e) => {
+                fail!(e, "failed to list permissions: {e}");
+                if !several {
+                    return ExitCode::FAILURE;
+                }
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        filter.retain(&mut ids);
+        if args.empty {
+            let (empty, failed) = empty_resources(proxy, table, &ids, args.no_data_only).await;
+            if failed {
+                code = ExitCode::FAILURE;
+            }
+            ids = empty;
+        }
+        sort.sort(&mut ids);
+        if args.reverse {
+            ids.reverse();
+        }
+
+        let total = ids.len();
+        if let Some(limit) = args.limit {
+            ids.truncate(limit);
+            if total > ids.len() && !QUIET.load(Ordering::Relaxed) {
+                let prefix = if several {
+                    format!("{table}: ")
+                } else {
+                    String::new()
+                };
+                eprintln!("{prefix}… and {} more", total - ids.len());
+            }
+        }
+        listings.push(Listing {
+            table,
+            total,
+            resources: ids,
+        });
+    }
+
+    if format == Format::Json && (args.count || !details) {
+        print_listings_json(&listings, args.count, args.limit.is_some());
+        return code;
+    }
+    if args.count {
+        print_list_count(&listings);
+        return code;
+    }
+    if !details {
+        print_list_response(&listings);
+        return code;
+    }
+
+    let mut resource_listings = Vec::with_capacity(listings.len());
+    for listing in listings {
+        let mut resources = Vec::with_capacity(listing.resources.len());
+        for (id, result) in lookup_all(proxy, listing.table, &listing.resources).await {
+            let resource = match result {
+                Ok((apps, data)) => ResourceDetails {
+                    id,
+                    apps: Some(apps.into_iter().collect()),
+                    data: args.with_data.then_some(data),
+                },
+                // deleted since we listed it
+                Err(e) if is_not_found(&e) => ResourceDetails {
+                    id,
+                    apps: None,
+                    data: None,
+                },
+                Err(e) => {
+                    fail!(e, "{id}: failed to lookup permissions: {e}");
+                    code = ExitCode::FAILURE;
+                    continue;
+                }
+            };
+            resources.push(resource);
+        }
+        resource_listings.push(Listing {
+            table: listing.table,
+            total: listing.total,
+            resources,
+        });
+    }
+
+    if format == Format::Json {
+        print_listings_json(&resource_listings, false, args.limit.is_some());
+        return code;
+    }
+    print_resource_details(
+        &resource_listings,
+        args.with_data,
+        sort == ListSort::Permissions,
+        args.reverse,
+    );
+    code
+}
+
+/// The store hands out the byte 0 as the data of resources that never had any.
+fn data_is_unset(data: &Value<'_>) -> bool {
+    matches!(data, Value::U8(0))
+}
+
+/// Keep the `ids` without application entries, and without data too with
+/// `no_data_only`. Also returns whether any lookup failed.
+async fn empty_resources(
+    proxy: &Store<'_>,
+    table: &str,
+    ids: &[String],
+    no_data_only: bool,
+) -> (Vec<String>, bool) {
+    let (mut empty, mut failed) = (Vec::new(), false);
+    for (id, result) in lookup_all(proxy, table, ids).await {
+        match result {
+            Ok((apps, data)) if apps.is_empty() && (!no_data_only || data_is_unset(&data)) => {
+                empty.push(id);
+            }
+            Ok(_) => {}
+            // deleted since we listed it
+            Err(e) if is_not_found(&e) => {}
+            Err(e) => {
+                fail!(e, "{id}: failed to lookup permissions: {e}");
+                failed = true;
+            }
+        }
+    }
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!(
+            "checked {} ids in {table}, {} empty",
+            ids.len(),
+            empty.len()
+        );
+    }
+    (empty, failed)
+}
+
+async fn grep_permission(proxy: &Store<'_>, args: &GrepArgs, format: Format) -> ExitCode {
+    let ids = match proxy.list(&args.table).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            fail!(e, "failed to list permissions: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    let mut rows = Vec::new();
+    for (id, result) in lookup_all(proxy, &args.table, &ids).await {
+        let (apps, _) = match result {
+            Ok(response) => response,
+            // the resource was deleted while scanning
+            Err(e) if is_not_found(&e) => continue,
+            Err(e) => {
+                fail!(e, "failed to lookup {id}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        let mut apps: Vec<_> = apps.into_iter().collect();
+        apps.sort();
+        for (app, permissions) in apps {
+            let found = permissions
+                .iter()
+                .any(|permission| args.matching.matches(&args.permission, permission));
+            if found != args.invert {
+                rows.push(EntryRow {
+                    table: None,
+                    id: id.clone(),
+                    app,
+                    permissions,
+                });
+            }
+        }
+    }
+
+    print_entries(&rows, format);
+    code
+}
+
+async fn where_app(proxy: &Store<'_>, args: &WhereArgs, format: Format) -> ExitCode {
+    let tables = if args.tables.is_empty() {
+        known_tables()
+    } else {
+        args.tables.clone()
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    let mut rows = Vec::new();
+    for table in tables {
+        let ids = match proxy.list(&table).await {
+            Ok(ids) => ids,
+            // the table has never been created on this system
+            Err(e) if is_not_found(&e) => continue,
+            Err(e) => {
+                fail!(e, "failed to list {table}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        for (id, result) in lookup_all(proxy, &table, &ids).await {
+            match result {
+                Ok((mut apps, _)) => {
+                    if let Some(permissions) = apps.remove(&args.app) {
+                        rows.push(EntryRow {
+                            table: Some(table.clone()),
+                            id,
+                            app: args.app.clone(),
+                            permissions,
+                        });
+                    }
+                }
+                Err(e) if is_not_found(&e) => {}
+                Err(e) => {
+                    fail!(e, "failed to lookup {id} in {table}: {e}");
+                    code = ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+
+    print_entries(&rows, format);
+    code
+}
+
+async fn copy_app(proxy: &Store<'_>, args: &CopyAppArgs, format: Format) -> ExitCode {
+    let Some((id, src, dst)) = args.resolve() else {
+        if args.all_ids {
+            fail!(
+                ErrorKind::InvalidInput,
+                "expected <SRC_APP> <DST_APP> with --all-ids"
+            );
+        } else {
+            fail!(ErrorKind::InvalidInput, "expected <ID> <SRC_APP> <DST_APP>");
+        }
+        return ExitCode::FAILURE;
+    };
+    // writing the destination and deleting the source would lose the entry
+    if src == dst {
+        fail!(ErrorKind::InvalidInput, "cannot copy {src} onto itself");
+        return ExitCode::FAILURE;
+    }
+
+    let ids = match id {
+        Some(id) => vec![id.to_string()],
+        None => match proxy.list(&args.table).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                fail!(e, "failed to list permissions: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    let mut changes = ChangeLog::new(proxy, &args.table, &args.diff);
+    let mut code = ExitCode::SUCCESS;
+    let mut copied = 0;
+    for (id, result) in lookup_all(proxy, &args.table, &ids).await {
+        let apps = match result {
+            Ok((apps, _)) => apps,
+            Err(e) => {
+                fail!(e, "failed to lookup {id}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        let Some(permissions) = apps.get(src) else {
+            if !args.all_ids {
+                fail!(ErrorKind::NotFound, "{src} has no entry for {id}");
+                code = ExitCode::FAILURE;
+            }
+            continue;
+        };
+
+        if apps.contains_key(dst) && !args.overwrite {
+            fail!(
+                ErrorKind::AlreadyExists,
+                "{dst} already has an entry for {id}, pass --overwrite to replace it"
+            );
+            code = ExitCode::FAILURE;
+            continue;
+        }
+
+        let before = changes.before(&id).await;
+        if let Err(e) = proxy
+            .set_permission(&args.table, false, &id, dst, permissions)
+            .await
+        {
+            fail!(e, "failed to set permissions on {id}: {e}");
+            code = ExitCode::FAILURE;
+            continue;
+        }
+
+        if args.remove_source
+            && let Err(e) = proxy.delete_permission(&args.table, &id, src).await
+        {
+            fail!(e, "failed to delete permissions of {src} on {id}: {e}");
+            code = ExitCode::FAILURE;
+        }
+
+        let touched: &[&str] = if args.remove_source {
+            &[src, dst]
+        } else {
+            &[dst]
+        };
+        changes.after(&id, Some(touched), before).await;
+        copied += 1;
+    }
+
+    status!("Copied permissions of {src} to {dst} on {copied} resource(s)");
+    changes.print(format);
+    code
+}
+
+#[derive(Serialize, Debug, Default)]
+struct PermissionDiff {
+    only_a: Vec<String>,
+    only_b: Vec<String>,
+    shared: Vec<String>,
+}
+
+impl PermissionDiff {
+    fn new(a: &[String], b: &[String]) -> Self {
+        let mut diff = Self::default();
+        for permission in a {
+            if b.contains(permission) {
+                diff.shared.push(permission.clone());
+            } else {
+                diff.only_a.push(permission.clone());
+            }
+        }
+        diff.only_b = b.iter().filter(|p| !a.contains(p)).cloned().collect();
+        diff
+    }
+}
+
+async fn diff_apps(
+    proxy: &Store<'_>,
+    args: &DiffArgs,
+    app_a: &str,
+    app_b: &str,
+    format: Format,
+) -> ExitCode {
+    let mut lists = Vec::with_capacity(2);
+    for app in [app_a, app_b] {
+        match proxy.get_permission(&args.table, &args.id, app).await {
+            Ok(permissions) => lists.push(permissions),
+            Err(e) => {
+                fail!(e, "failed to get permissions of {app}: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let diff = PermissionDiff::new(&lists[0], &lists[1]);
+    if format == Format::Json {
+        print_json(&diff);
+        return ExitCode::SUCCESS;
+    }
+
+    let mut table = new_table();
+    table.set_header(vec![
+        format!("Only {app_a}"),
+        format!("Only {app_b}"),
+        "Shared".to_string(),
+    ]);
+
+    let rows = diff
+        .only_a
+        .len()
+        .max(diff.only_b.len())
+        .max(diff.shared.len());
+    for i in 0..rows {
+        let cell = |column: &[String]| Cell::new(column.get(i).map_or("", String::as_str));
+        table.add_row([
+            cell(&diff.only_a).fg(Color::Red),
+            cell(&diff.only_b).fg(Color::Green),
+            cell(&diff.shared),
+        ]);
+    }
+
+    println!("{table}");
+    ExitCode::SUCCESS
+}
+
+async fn diff_snapshot(proxy: &Store<'_>, args: &DiffArgs, path: &str, format: Format) -> ExitCode {
+    let snapshot = match snapshot::ResourceSnapshot::load(path) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            fail!(e, "failed to load snapshot: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let live = match proxy.lookup(&args.table, &args.id).await {
+        Ok((permissions, data)) => snapshot::ResourceSnapshot::from_lookup(permissions, &data),
+        Err(e) => {
+            fail!(e, "failed to lookup permissions: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let diff = snapshot::ResourceDiff::new(&snapshot, &live);
+    if format == Format::Json {
+        print_json(&diff);
+    } else {
+        diff.print();
+    }
+
+    if diff.is_empty() || args.exit_zero {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn compare_snapshots(args: &CompareArgs, format: Format) -> ExitCode {
+    let mut snapshots = Vec::with_capacity(2);
+    for path in [&args.before, &args.after] {
+        match snapshot::TableSnapshot::load(path) {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => {
+                fail!(e, "failed to load snapshot: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if snapshots[0].table != snapshots[1].table {
+        eprintln!(
+            "warning: comparing snapshots of different tables ({} and {})",
+            snapshots[0].table, snapshots[1].table
+        );
+    }
+
+    let diff = snapshot::TableDiff::new(&snapshots[0], &snapshots[1]);
+    if format == Format::Json {
+        print_json(&diff);
+    } else {
+        diff.print();
+    }
+
+    if diff.is_empty() || args.exit_zero {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+async fn write_snapshot(
+    proxy: &Store<'_>,
+    args: &SnapshotArgs,
+    default_path: Option<&String>,
+    mkdir: bool,
+) -> ExitCode {
+    let Some((tables, file)) = args.resolve() else {
+        if args.all_tables {
+            fail!(
+                ErrorKind::InvalidInput,
+                "expected at most a <DIR> with --all-tables"
+            );
+        } else {
+            fail!(ErrorKind::InvalidInput, "expected <TABLE> [FILE]");
+        }
+        return ExitCode::FAILURE;
+    };
+    let file = file.or(default_path.map(String::as_str));
+    let filter = match args.filter.compile(&args.matching) {
+        Ok(filter) => filter,
+        Err(e) => {
+            fail!(e, "{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let filter = &filter;
+
+    // buffered keeps the order of the tables, whichever finishes first
+    let captures: Vec<_> = stream::iter(&tables)
+        .map(|table| async move {
+            (
+                table,
+                snapshot::TableSnapshot::capture(proxy, table, filter).await,
+            )
+        })
+        .buffered(args.jobs as usize)
+        .collect()
+        .await;
+
+    let mut code = ExitCode::SUCCESS;
+    for (table, capture) in captures {
+        let snapshot = match capture {
+            Ok(snapshot) => snapshot,
+            // the portal owning it never ran here
+            Err(e) if args.all_tables && is_not_found(&e) => continue,
+            Err(e) => {
+                fail!(e, "failed to capture {table}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        let name = format!("{}-{}.json", snapshot.table, snapshot.timestamp);
+        let path = match file {
+            Some(dir) if args.all_tables => format!("{}/{name}", dir.trim_end_matches('/')),
+            Some(path) => path.to_string(),
+            None => name,
+        };
+        if !save_snapshot(&snapshot, &path, mkdir) {
+            code = ExitCode::FAILURE;
+        }
+    }
+    code
+}
+
+/// Write `snapshot` to `path`, reporting a failure.
+fn save_snapshot(snapshot: &snapshot::TableSnapshot, path: &str, mkdir: bool) -> bool {
+    if mkdir && let Err(e) = output::create_parent(path) {
+        fail!(e, "failed to create the directory of {path}: {e}");
+        return false;
+    }
+
+    let result = serde_json::to_string_pretty(snapshot)
+        .map_err(|e| e.to_string())
+        .and_then(|json| std::fs::write(path, json + "\n").map_err(|e| e.to_string()));
+    match result {
+        Ok(_) => {
+            status!("Snapshot of {} written to {path}", snapshot.table);
+            true
+        }
+        Err(e) => {
+            fail!(e, "failed to write {path}: {e}");
+            false
+        }
+    }
+}
+
+/// Replace the entries and data of the resources in a snapshot with the
+/// captured ones.
+async fn restore_snapshot(proxy: &Store<'_>, args: &RestoreArgs) -> ExitCode {
+    let snapshot = match snapshot::TableSnapshot::load(&args.file) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            fail!(e, "failed to load the snapshot: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let table = args.table.as_deref().unwrap_or(&snapshot.table);
+    if let Some(id) = args
+        .ids
+        .iter()
+        .find(|id| !snapshot.resources.contains_key(*id))
+    {
+        let e = format!("{} has no resource {id}", args.file);
+        fail!(e, "{e}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    let mut restored = 0;
+    let resources = snapshot
+        .resources
+        .iter()
+        .filter(|(id, _)| args.ids.is_empty() || args.ids.contains(id));
+    // resources with bad data never wrote, so never created the table
+    let mut attempted = false;
+    for (id, resource) in resources {
+        let (permissions, data) = match resource.to_lookup() {
+            Ok(response) => response,
+            Err(e) => {
+                fail!(e, "{id}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        // only the first write needs to create the table
+        let create = args.create && !attempted;
+        attempted = true;
+        let mut result = proxy.set(table, create, id, &permissions, &data).await;
+        if !create && args.create && result.as_ref().is_err_and(is_not_found) {
+            // another client may have deleted the table since, retry once
+            result = proxy.set(table, true, id, &permissions, &data).await;
+        }
+        match result {
+            Ok(_) => restored += 1,
+            Err(e) => {
+                fail!(e, "{id}: failed to restore: {e}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    status!(
+        "Restored {restored} resource(s) of {table} from {}",
+        args.file
+    );
+    code
+}
+
+/// A single application entry of a resource, as printed by the scanning commands.
+#[derive(Serialize, Debug)]
+struct EntryRow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    table: Option<String>,
+    id: String,
+    app: String,
+    permissions: Vec<String>,
+}
+
+/// What `list` kept of one table, `total` counting the resources that
+/// `--limit` left out too.
+struct Listing<'a, T> {
+    table: &'a str,
+    total: usize,
+    resources: Vec<T>,
+}
+
+impl<T> Listing<'_, T> {
+    fn json(&self, count: bool, limited: bool) -> ListJson<'_, T> {
+        if count {
+            ListJson::Count { total: self.total }
+        } else if limited {
+            ListJson::Limited {
+                total: self.total,
+                resources: &self.resources,
+            }
+        } else {
+            ListJson::All(&self.resources)
+        }
+    }
+}
+
+/// The JSON output of `list` for one table.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+enum ListJson<'a, T> {
+    /// With `--count`
+    Count {
+        total: usize,
+    },
+    /// With `--limit`
+    Limited {
+        total: usize,
+        resources: &'a [T],
+    },
+    All(&'a [T]),
+}
+
+/// A resource with its entries, as printed by `list --details`.
+#[derive(Serialize, Debug)]
+struct ResourceDetails {
+    id: String,
+    /// `None` once the resource is gone
+    apps: Option<BTreeMap<String, Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<OwnedValue>,
+}
+
+#[derive(Serialize, Debug)]
+struct LookupJson<'a> {
+    permissions: BTreeMap<&'a str, &'a Vec<String>>,
+    /// The number of permissions of every application, with `--counts`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counts: Option<BTreeMap<&'a str, usize>>,
+    /// `None` when the resource has no associated data
+    data: Option<&'a OwnedValue>,
+}
+
+/// The JSON output of lookup with several ids, an object keyed by id in the
+/// order of the arguments.
+#[derive(Debug)]
+struct LookupsJson<'a>(Vec<(&'a str, Option<LookupJson<'a>>)>);
+
+impl Serialize for LookupsJson<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(id, response)| (id, response)))
+    }
+}
+
+impl<'a> LookupJson<'a> {
+    fn new((permissions, data): &'a LookupResponse, counts: bool) -> Self {
+        let counts = counts.then(|| {
+            permissions
+                .iter()
+                .map(|(app, permissions)| (app.as_str(), permissions.len()))
+                .collect()
+        });
+        Self {
+            permissions: permissions
+                .iter()
+                .map(|(app, permissions)| (app.as_str(), permissions))
+                .collect(),
+            counts,
+            data: (!data_is_unset(data)).then_some(data),
+        }
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{json}"),
+        Err(e) => fail!(e, "failed to serialize output: {e}"),
+    }
+}
+
+fn print_entries(rows: &[EntryRow], format: Format) {
+    if format == Format::Json {
+        print_json(rows);
+        return;
+    }
+
+    let with_table = rows.iter().any(|row| row.table.is_some());
+    let mut table = new_table();
+    if with_table {
+        table.set_header(vec!["Table", "Resource ID", "AppID", "Permissions"]);
+    } else {
+        table.set_header(vec!["Resource ID", "AppID", "Permissions"]);
+    }
+
+    for row in rows {
+        let mut cells = Vec::new();
+        if with_table {
+            cells.push(Cell::new(row.table.as_deref().unwrap_or_default()));
+        }
+        cells.extend([
+            Cell::new(cell_text(&row.id)),
+            Cell::new(cell_text(&row.app)),
+            permissions_cell(&row.permissions),
+        ]);
+        table.add_row(cells);
+    }
+
+    println!("{table}");
+}
+
+async fn merge_apps(proxy: &Store<'_>, args: &MergeArgs) -> ExitCode {
+    let (src, dst) = (&args.src_app, &args.dst_app);
+    // --remove-src would delete the merged entry
+    if src == dst {
+        let e = format!("cannot merge {src} into itself");
+        fail!(e, "{e}");
+        return ExitCode::FAILURE;
+    }
+    let mut apps = match proxy.lookup(&args.table, &args.id).await {
+        Ok((apps, _)) => apps,
+        Err(e) => {
+            fail!(e, "failed to lookup permissions: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let source = apps.remove(src).unwrap_or_default();
+    let mut merged = apps.remove(dst).unwrap_or_default();
+    for permission in source {
+        if !merged.contains(&permission) {
+            merged.push(permission);
+        }
+    }
+
+    if let Err(e) = proxy
+        .set_permission(&args.table, false, &args.id, dst, &merged)
+        .await
+    {
+        fail!(e, "failed to set permissions: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    if args.remove_src
+        && let Err(e) = proxy.delete_permission(&args.table, &args.id, src).await
+    {
+        fail!(e, "failed to delete permissions of {src}: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    status!("Merged {src} into {dst}: {}", merged.join(","));
+    ExitCode::SUCCESS
+}
+
+async fn move_resource(proxy: &Store<'_>, args: &MoveArgs) -> ExitCode {
+    let (old_id, new_id) = (&args.old_id, &args.new_id);
+    // deleting the source would delete what was just written
+    if old_id == new_id {
+        let e = format!("cannot move {old_id} onto itself");
+        fail!(e, "{e}");
+        return ExitCode::FAILURE;
+    }
+    let (permissions, data) = match proxy.lookup(&args.table, old_id).await {
+        Ok(response) => response,
+        Err(e) => {
+            fail!(e, "failed to lookup {old_id}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match proxy.lookup(&args.table, new_id).await {
+        Ok(_) if !args.force => {
+            fail!(
+                ErrorKind::AlreadyExists,
+                "{new_id} already exists, pass --force to replace it"
+            );
+            return ExitCode::FAILURE;
+        }
+        Ok(_) => {}
+        Err(e) if is_not_found(&e) => {}
+        Err(e) => {
+            fail!(e, "failed to lookup {new_id}: {e}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    // Set replaces the whole entry, so a forced move never merges with the old target
+    if let Err(e) = proxy
+        .set(&args.table, false, new_id, &permissions, &data)
+        .await
+    {
+        fail!(e, "failed to write {new_id}: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    match proxy.lookup(&args.table, new_id).await {
+        Ok((written, written_data)) if written == permissions && written_data == data => {}
+        Ok(_) => {
+            fail!(
+                ErrorKind::Mismatch,
+                "{new_id} does not match {old_id} after writing, keeping {old_id}"
+            );
+            return ExitCode::FAILURE;
+        }
+        Err(e) => {
+            fail!(e, "failed to verify {new_id}, keeping {old_id}: {e}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if args.keep_source {
+        status!("Copied {old_id} to {new_id} successfully");
+        return ExitCode::SUCCESS;
+    }
+
+    match proxy.delete(&args.table, old_id).await {
+        Ok(_) => {
+            status!("Moved {old_id} to {new_id} successfully");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            fail!(e, "failed to delete {old_id}: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn rename_app(proxy: &Store<'_>, args: &RenameAppArgs) -> ExitCode {
+    let (old_app, new_app) = (&args.old_app, &args.new_app);
+    if args.onto_itself() {
+        fail!(ErrorKind::InvalidInput, "cannot rename {old_app} to itself");
+        return ExitCode::FAILURE;
+    }
+    let mut apps = match proxy.lookup(&args.table, &args.id).await {
+        Ok((apps, _)) => apps,
+        Err(e) => {
+            fail!(e, "failed to lookup permissions: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let Some(permissions) = apps.remove(old_app) else {
+        fail!(
+            ErrorKind::NotFound,
+            "{old_app} has no entry for {}",
+            args.id
+        );
+        return ExitCode::FAILURE;
+    };
+
+    if apps.contains_key(new_app) && !args.force {
+        fail!(
+            ErrorKind::AlreadyExists,
+            "{new_app} already has an entry for {}, pass --force to replace it",
+            args.id
+        );
+        return ExitCode::FAILURE;
+    }
+
+    if let Err(e) = proxy
+        .set_permission(&args.table, false, &args.id, new_app, &permissions)
+        .await
+    {
+        fail!(e, "failed to set permissions: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    match proxy.get_permission(&args.table, &args.id, new_app).await {
+        Ok(written) if written == permissions => {}
+        Ok(written) => {
+            fail!(
+                ErrorKind::Mismatch,
+                "read back [{}] for {new_app} but wrote [{}], keeping {old_app}",
+                written.join(","),
+                permissions.join(",")
+            );
+            return ExitCode::FAILURE;
+        }
+        Err(e) => {
+            fail!(
+                e,
+                "failed to verify permissions of {new_app}, keeping {old_app}: {e}"
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+
+    match proxy
+        .delete_permission(&args.table, &args.id, old_app)
+        .await
+    {
+        Ok(_) => {
+            status!("Renamed {old_app} to {new_app} successfully");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            fail!(e, "failed to delete permissions of {old_app}: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn purge_app(proxy: &Store<'_>, args: &PurgeAppArgs) -> ExitCode {
+    let Some((tables, app)) = args.resolve() else {
+        if args.all_tables {
+            fail!(ErrorKind::InvalidInput, "expected <APP> with --all-tables");
+        } else {
+            fail!(ErrorKind::InvalidInput, "expected <TABLE> <APP>");
+        }
+        return ExitCode::FAILURE;
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    let mut found = Vec::new();
+    for table in tables {
+        let ids = match proxy.list(&table).await {
+            Ok(ids) => ids,
+            Err(e) if args.all_tables && is_not_found(&e) => continue,
+            Err(e) => {
+                fail!(e, "failed to list {table}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        for (id, result) in lookup_all(proxy, &table, &ids).await {
+            match result {
+                Ok((apps, _)) if apps.contains_key(app) => found.push((table.clone(), id)),
+                Ok(_) => {}
+                Err(e) if is_not_found(&e) => {}
+                Err(e) => {
+                    fail!(e, "failed to lookup {id} in {table}: {e}");
+                    code = ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+
+    if found.is_empty() {
+        status!("no entries found for {app}");
+        return code;
+    }
+
+    for (table, id) in &found {
+        println!("{table}/{id}");
+    }
+    if args.dry_run {
+        return code;
+    }
+    if !args.yes && !confirm(&format!("Remove {app} from {} resource(s)?", found.len())) {
+        fail!(ErrorKind::Aborted, "aborted");
+        return ExitCode::FAILURE;
+    }
+
+    let mut cleaned = 0;
+    for (table, id) in &found {
+        match proxy.delete_permission(table, id, app).await {
+            Ok(_) => cleaned += 1,
+            Err(e) => {
+                fail!(e, "failed to clean {table}/{id}: {e}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    status!("Removed {app} from {cleaned} resource(s)");
+    code
+}
+
+fn print_lookup_response(response: &LookupResponse, args: &LookupArgs, format: Format) {
+    let counts = args.counts;
+    if format == Format::Json {
+        print_json(&LookupJson::new(response, counts));
+        return;
+    }
+
+    let mut apps: Vec<_> = response.0.iter().collect();
+    args.sort.sort(&mut apps);
+    if args.reverse {
+        apps.reverse();
+    }
+
+    let columns = match args.columns.as_slice() {
+        [] if counts => &[Column::App, Column::Permissions, Column::Count][..],
+        [] => &[Column::App, Column::Permissions][..],
+        columns => columns,
+    };
+
+    let mut table = new_table();
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        table.set_header(columns.iter().map(|column| match column {
+            Column::App => "AppID",
+            Column::Permissions => "Permissions",
+            Column::Count => "Count",
+        }));
+    }
+
+    for (app_id, allowed) in apps {
+        table.add_row(columns.iter().map(|column| match column {
+            Column::App => Cell::new(cell_text(app_id)),
+            Column::Permissions => permissions_cell(allowed),
+            Column::Count => Cell::new(allowed.len()),
+        }));
+    }
+
+    println!("{table}");
+    if format == Format::Markdown {
+        // otherwise it would be read as another row
+        println!();
+    }
+    // an empty dictionary prints as {}, apart from no data at all
+    if data_is_unset(&response.1) {
+        println!("(no associated data)");
+    } else {
+        print!("associated data:\n{}", variant::pretty(&response.1));
+    }
+}
+
+/// Print the JSON of `list`, keyed by table when listing several ones.
+fn print_listings_json<T: Serialize>(listings: &[Listing<'_, T>], count: bool, limited: bool) {
+    match listings {
+        [listing] => print_json(&listing.json(count, limited)),
+        listings => {
+            let tables: BTreeMap<&str, ListJson<'_, T>> = listings
+                .iter()
+                .map(|listing| (listing.table, listing.json(count, limited)))
+                .collect();
+            print_json(&tables);
+        }
+    }
+}
+
+fn print_list_count(listings: &[Listing<'_, String>]) {
+    if let [listing] = listings {
+        println!("{}", listing.total);
+        return;
+    }
+
+    let mut table = new_table();
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        table.set_header(vec!["Table", "Count"]);
+    }
+    for listing in listings {
+        table.add_row(vec![
+            cell_text(listing.table).into_owned(),
+            listing.total.to_string(),
+        ]);
+    }
+
+    println!("{table}");
+}
+
+fn print_list_response(listings: &[Listing<'_, String>]) {
+    let with_table = listings.len() > 1;
+    let mut table = new_table();
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        let header = ["Table", "Resource ID"];
+        table.set_header(&header[usize::from(!with_table)..]);
+    }
+
+    for listing in listings {
+        for id in &listing.resources {
+            let mut row = Vec::new();
+            if with_table {
+                row.push(cell_text(listing.table));
+            }
+            row.push(cell_text(id));
+            table.add_row(row);
+        }
+    }
+
+    println!("{table}");
+}
+
+/// Print `resources` in order, their applications sorted by permissions
+/// with `by_permissions`, and the rows of every resource reversed too with
+/// `reverse` so the whole output comes out backwards.
+fn print_resource_details(
+    listings: &[Listing<'_, ResourceDetails>],
+    with_data: bool,
+    by_permissions: bool,
+    reverse: bool,
+) {
+    let with_table = listings.len() > 1;
+    let mut table = new_table();
+    let mut header = vec!["Resource ID", "App", "Permissions"];
+    if with_table {
+        header.insert(0, "Table");
+    }
+    if with_data {
+        header.push("Data");
+    }
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        table.set_header(header);
+    }
+
+    let resources = listings.iter().flat_map(|listing| {
+        let name = with_table.then_some(listing.table);
+        listing
+            .resources
+            .iter()
+            .map(move |resource| (name, resource))
+    });
+    for (name, resource) in resources {
+        let row = |cells: Vec<Cell>| {
+            let name = name.map(|name| Cell::new(cell_text(name)));
+            name.into_iter().chain(cells).collect::<Vec<_>>()
+        };
+        let Some(apps) = &resource.apps else {
+            table.add_row(row(vec![
+                Cell::new(&resource.id),
+                Cell::new("(gone)").add_attribute(Attribute::Dim),
+            ]));
+            continue;
+        };
+
+        let data = resource.data.as_deref().map(Value::to_string);
+        if apps.is_empty() {
+            let mut cells = vec![Cell::new(&resource.id), Cell::new(""), Cell::new("")];
+            cells.extend(data.as_deref().map(Cell::new));
+            table.add_row(row(cells));
+        }
+        let mut apps: Vec<_> = apps.iter().collect();
+        if by_permissions {
+            // stable, so equal permissions stay sorted by application
+            apps.sort_by_key(|(_, permissions)| *permissions);
+        }
+        if reverse {
+            apps.reverse();
+        }
+        for (app, permissions) in apps {
+            let mut cells = vec![
+                Cell::new(&resource.id),
+                Cell::new(app),
+                permissions_cell(permissions),
+            ];
+            cells.extend(data.as_deref().map(Cell::new));
+            table.add_row(row(cells));
+        }
+    }
+
+    println!("{table}");
+}
+
+/// Print the permissions of several applications side by side, from a single
+/// lookup of the resource.
+///
+/// With `keyed`, every resource of `ids` gets a heading, and the JSON output
+/// is keyed by resource ID.
+async fn get_permissions(
+    proxy: &Store<'_>,
+    table: &str,
+    ids: &[String],
+    apps: &[&str],
+    keyed: bool,
+    by_app: bool,
+    format: Format,
+) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    let mut resources = Vec::with_capacity(ids.len());
+    for id in ids {
+        match proxy.lookup(table, id).await {
+            Ok((entries, _)) => resources.push((id.as_str(), entries)),
+            Err(e) if is_not_found(&e) => resources.push((id.as_str(), HashMap::new())),
+            Err(e) => {
+                fail!(e, "{id}: failed to lookup permissions: {e}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if format == Format::Json && by_app {
+        let by_app = |entries: &HashMap<String, Vec<String>>| -> BTreeMap<String, Vec<String>> {
+            let entries = app_entries(entries, apps).into_iter();
+            entries
+                .map(|(app, permissions)| {
+                    (app.to_string(), permissions.cloned().unwrap_or_default())
+                })
+                .collect()
+        };
+        match resources.as_slice() {
+            [(_, entries)] if !keyed => print_json(&by_app(entries)),
+            resources => {
+                let json: BTreeMap<&str, _> = resources
+                    .iter()
+                    .map(|(id, entries)| (*id, by_app(entries)))
+                    .collect();
+                print_json(&json);
+            }
+        }
+        return code;
+    }
+    if format == Format::Json {
+        match resources.as_slice() {
+            [(_, entries)] if !keyed => print_json(&app_entries(entries, apps)),
+            resources => {
+                let json: BTreeMap<&str, _> = resources
+                    .iter()
+                    .map(|(id, entries)| (*id, app_entries(entries, apps)))
+                    .collect();
+                print_json(&json);
+            }
+        }
+        return code;
+    }
+
+    for (i, (id, entries)) in resources.iter().enumerate() {
+        if keyed {
+            if i > 0 {
+                println!();
+            }
+            println!("== {id} ==");
+        }
+        if by_app {
+            print_by_app(entries, apps);
+        } else {
+            print_side_by_side(entries, apps);
+        }
+    }
+    code
+}
+
+fn print_by_app(entries: &HashMap<String, Vec<String>>, apps: &[&str]) {
+    let mut table = new_table();
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        table.set_header(vec!["App", "Permissions"]);
+    }
+    for app in apps {
+        let permissions = entries.get(*app).map(Vec::as_slice).unwrap_or_default();
+        table.add_row(vec![
+            Cell::new(cell_text(app)),
+            permissions_cell(permissions),
+        ]);
+    }
+
+    println!("{table}");
+}
+
+/// The entries of `apps`, `None` for the ones without any.
+fn app_entries<'a>(
+    entries: &'a HashMap<String, Vec<String>>,
+    apps: &[&'a str],
+) -> BTreeMap<&'a str, Option<&'a Vec<String>>> {
+    apps.iter().map(|app| (*app, entries.get(*app))).collect()
+}
+
+fn print_side_by_side(entries: &HashMap<String, Vec<String>>, apps: &[&str]) {
+    // in the order the applications first hold them
+    let mut permissions: Vec<&str> = Vec::new();
+    for permission in apps.iter().filter_map(|app| entries.get(*app)).flatten() {
+        if !permissions.contains(&permission.as_str()) {
+            permissions.push(permission);
+        }
+    }
+
+    let mut table = new_table();
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        table.set_header(std::iter::once("Permission").chain(apps.iter().copied()));
+    }
+    for permission in permissions {
+        let marks = apps.iter().map(|app| match entries.get(*app) {
+            Some(held) if held.iter().any(|p| p == permission) => Cell::new("✓").fg(Color::Green),
+            Some(_) => Cell::new("✗").fg(Color::Red),
+            None => Cell::new("(absent)").add_attribute(Attribute::Dim),
+        });
+        table.add_row(std::iter::once(Cell::new(cell_text(permission))).chain(marks));
+    }
+    if table.is_empty() && apps.iter().any(|app| !entries.contains_key(*app)) {
+        // still tell the applications without an entry apart
+        let marks = apps.iter().map(|app| match entries.get(*app) {
+            Some(_) => Cell::new(""),
+            None => Cell::new("(absent)").add_attribute(Attribute::Dim),
+        });
+        table.add_row(std::iter::once(Cell::new("")).chain(marks));
+    }
+
+    println!("{table}");
+}
+
+/// Print the permissions of a single application, telling an application
+/// without an entry from one granted nothing.
+async fn get_permission(
+    proxy: &Store<'_>,
+    table: &str,
+    id: &str,
+    app: &str,
+    suggest: bool,
+    format: Format,
+) -> ExitCode {
+    let permissions = match proxy.get_permission(table, id, app).await {
+        Ok(permissions) => permissions,
+        Err(e) => {
+            fail!(e, "failed to get permissions: {e}");
+            if suggest && format != Format::Json && is_not_found(&e) {
+                hint_not_found(proxy, table, id).await;
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+    if !permissions.is_empty() {
+        print_get_permission_response(&permissions, format);
+        return ExitCode::SUCCESS;
+    }
+
+    // GetPermission answers both with an empty list, only Lookup has the keys
+    let present = match proxy.lookup(table, id).await {
+        Ok((apps, _)) => apps.contains_key(app),
+        Err(e) => {
+            fail!(e, "failed to lookup {id}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if format == Format::Json {
+        if present {
+            print_json(&permissions);
+        } else {
+            print_json(&None::<Vec<String>>);
+        }
+    } else if present {
+        println!("{app} is granted no permissions on {id}");
+    } else {
+        fail!(ErrorKind::NotFound, "no entry for {app} on {id}");
+    }
+    if present {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(GET_NO_ENTRY)
+    }
+}
+
+fn print_get_permission_response(response: &[String], format: Format) {
+    if format == Format::Json {
+        print_json(response);
+        return;
+    }
+
+    let mut table = new_table();
+    if !NO_HEADER.load(Ordering::Relaxed) {
+        table.set_header(vec!["Permission"]);
+    }
+
+    for permission in response {
+        table.add_row(vec![permissions_cell(std::slice::from_ref(permission))]);
+    }
+
+    println!("{table}");
+}
+
+/// Parse a command line, also returning the name of the chosen subcommand.
+fn parse_cli<I, T>(args: I) -> Result<(Cli, String), clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = Cli::command().try_get_matches_from(args)?;
+    let command = matches.subcommand_name().unwrap_or_default().to_string();
+    Ok((Cli::from_arg_matches(&matches)?, command))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let (cli, command) = parse_cli(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    init_tracing();
+    let _output = match prepare(&cli) {
+        Ok(output) => output,
+        Err(code) => return code,
+    };
+    if !config::load() {
+        return ExitCode::FAILURE;
+    }
+    match &cli.command {
+        Subcommands::Compare(args) => return compare_snapshots(args, cli.format),
+        Subcommands::Explain(args) => return tables::explain(args.table.as_deref(), cli.format),
+        Subcommands::Tables(TablesArgs { probe: false }) => {
+            return tables::tables(None, cli.format).await;
+        }
+        _ => {}
+    }
+
+    let Some(proxy) = connect().await else {
+        return ExitCode::FAILURE;
+    };
+
+    if matches!(
+        cli.command,
+        Subcommands::BenchLookup(_) | Subcommands::Repl | Subcommands::Watch(_)
+    ) {
+        interrupt::install();
+    }
+    match &cli.command {
+        Subcommands::Repl => repl(&proxy).await,
+        _ => run(&proxy, &cli, &command).await,
+    }
+}
+
+/// Get ready to run a command line, from the shell or the REPL: redirect
+/// stdout and apply the global options.
+fn prepare(cli: &Cli) -> Result<Option<output::Redirect>, ExitCode> {
+    let output = redirect_output(cli)?;
+    apply_global_options(cli);
+    Ok(output)
+}
+
+/// Store the global options where the helpers read them, once stdout is
+/// where `--color auto` should look.
+fn apply_global_options(cli: &Cli) {
+    QUIET.store(cli.quiet, Ordering::Relaxed);
+    VERBOSITY.store(cli.verbose, Ordering::Relaxed);
+    MAX_WIDTH.store(cli.max_width.unwrap_or(0), Ordering::Relaxed);
+    MARKDOWN.store(cli.format == Format::Markdown, Ordering::Relaxed);
+    *STYLE.lock().unwrap_or_else(PoisonError::into_inner) = cli.style;
+    NO_HEADER.store(cli.no_header, Ordering::Relaxed);
+    COLOR.store(cli.color.enabled(), Ordering::Relaxed);
+    ALLOW_INVALID_APP_ID.store(cli.allow_invalid_app_id, Ordering::Relaxed);
+    TIMING.store(cli.timing, Ordering::Relaxed);
+    error::set_json(cli.format == Format::Json);
+}
+
+/// Send stdout to `--output` for the rest of the command. The REPL keeps its
+/// prompt on the terminal, and snapshots write the file themselves.
+fn redirect_output(cli: &Cli) -> Result<Option<output::Redirect>, ExitCode> {
+    let Some(path) = &cli.output else {
+        return Ok(None);
+    };
+    if matches!(cli.command, Subcommands::Repl | Subcommands::Snapshot(_)) {
+        return Ok(None);
+    }
+    match output::Redirect::to(path, cli.mkdir) {
+        Ok(output) => Ok(Some(output)),
+        Err(e) => {
+            fail!(e, "failed to open {path}: {e}");
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Collect the output of the command to page it once it is complete, the
+/// tables still wrapping to the terminal.
+fn start_pager() -> Option<output::Pager> {
+    let (columns, _) = output::terminal_size()?;
+    if MAX_WIDTH.load(Ordering::Relaxed) == 0 {
+        MAX_WIDTH.store(columns, Ordering::Relaxed);
+    }
+    output::Pager::start()
+}
+
+/// The `SERVICE_HINT` on a line of its own when `error` is about the missing
+/// permission store, part of the failure so that JSON reports carry it too.
+fn service_hint(error: &zbus::Error) -> String {
+    if is_service_unknown(error) {
+        format!("\n{SERVICE_HINT}")
+    } else {
+        String::new()
+    }
+}
+
+/// Connect to the session bus and check the permission store is usable.
+///
+/// The proxy keeps the connection alive, create it once and hand it to every
+/// operation instead of reconnecting.
+async fn connect() -> Option<PermissionStoreProxy<'static>> {
+    let connection = match Connection::session().await {
+        Ok(connection) => connection,
+        Err(e) => {
+            fail!(e, "Failed to connect: {e}");
+            return None;
+        }
+    };
+
+    let proxy = match PermissionStoreProxy::new(&connection).await {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            let hint = service_hint(&e);
+            fail!(e, "Failed to create proxy: {e}{hint}");
+            return None;
+        }
+    };
+
+    let server_version = match proxy.version().await {
+        Ok(version) => version,
+        Err(e) => {
+            let hint = service_hint(&e);
+            fail!(e, "Failed to get server version: {e}{hint}");
+            return None;
+        }
+    };
+
+    if server_version != PERMISSION_STORE_SPEC_VER {
+        fail!(
+            ErrorKind::Dbus,
+            "Server version {server_version} does not match expected version {PERMISSION_STORE_SPEC_VER}"
+        );
+        return None;
+    }
+
+    Some(proxy)
+}
+
+async fn repl(proxy: &PermissionStoreProxy<'_>) -> ExitCode {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            fail!(ErrorKind::Io, "Failed to start repl: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    loop {
+        let line = match editor.readline("xdp-perm> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                fail!(ErrorKind::Io, "failed to read line: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+
+        if matches!(line, "quit" | "exit") {
+            break;
+        }
+
+        let Some(words) = shlex::split(line) else {
+            fail!(ErrorKind::InvalidInput, "unterminated quote in input");
+            continue;
+        };
+
+        match parse_cli(std::iter::once("xdp-perm".to_string()).chain(words)) {
+            Ok((cli, command)) => {
+                let Ok(_output) = prepare(&cli) else {
+                    continue;
+                };
+                run(proxy, &cli, &command).await;
+                if interrupt::interrupted() {
+                    return ExitCode::from(interrupt::EXIT_CODE);
+                }
+            }
+            Err(e) => {
+                let _ = e.print();
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+async fn run(proxy: &PermissionStoreProxy<'_>, cli: &Cli, command: &str) -> ExitCode {
+    let audit = cli.audit_log.as_deref().map(audit::AuditLog::new);
+    let proxy = &Store::new(proxy, audit, command);
+    let format = cli.format;
+    let _pager = match &cli.command {
+        Subcommands::List(_) | Subcommands::Lookup(_) if !cli.no_pager => start_pager(),
+        _ => None,
+    };
+    match &cli.command {
+        Subcommands::Apps(args) => {
+            apps::apps(proxy, &args.table, args.by_name, args.resources, format).await
+        }
+        Subcommands::Audit(args) => {
+            let Some((tables, permission)) = args.resolve() else {
+                if args.all_tables {
+                    fail!(
+                        ErrorKind::InvalidInput,
+                        "expected <PERMISSION> with --all-tables"
+                    );
+                } else {
+                    fail!(ErrorKind::InvalidInput, "expected <TABLE> <PERMISSION>");
+                }
+                return ExitCode::FAILURE;
+            };
+            apps::audit(
+                proxy,
+                &tables,
+                args.all_tables,
+                permission,
+                args.not,
+                format,
+            )
+            .await
+        }
+        Subcommands::Background(args) => {
+            background::background(proxy, args.action, args.app.as_deref(), format).await
+        }
+        Subcommands::BenchLookup(args) => {
+            bench::lookup(
+                proxy,
+                &args.table,
                 &args.id,
-                &args.app,
-                &args.permissions,
+                args.iterations as usize,
+                format,
             )
             .await
-        {
-            Ok(_) => println!("Permissions set successfully"),
-            Err(e) => eprintln!("failed to set permissions: {e}"),
+        }
+        Subcommands::Check(args) => check_permission(proxy, args).await,
+        Subcommands::ClearTable(args) => clear_table(proxy, args).await,
+        Subcommands::Compare(args) => compare_snapshots(args, format),
+        Subcommands::CompareApps(args) => {
+            let mut tables = known_tables();
+            for table in &args.tables {
+                if !tables.contains(table) {
+                    tables.push(table.clone());
+                }
+            }
+            apps::compare(proxy, &tables, &args.apps, args.diff_only, format).await
+        }
+        Subcommands::CopyApp(args) => copy_app(proxy, args, format).await,
+        Subcommands::Delete(args) => delete_permission(proxy, args, format).await,
+        Subcommands::Devices(args) => {
+            let app = args.app.as_deref();
+            devices::devices(proxy, args.action, args.device, app, format).await
+        }
+        Subcommands::Diff(args) => match (&args.snapshot, &args.app_a, &args.app_b) {
+            (Some(path), _, _) => diff_snapshot(proxy, args, path, format).await,
+            (None, Some(app_a), Some(app_b)) => diff_apps(proxy, args, app_a, app_b, format).await,
+            _ => {
+                fail!(
+                    ErrorKind::InvalidInput,
+                    "expected two applications or --snapshot"
+                );
+                ExitCode::FAILURE
+            }
         },
-    };
+        Subcommands::Get(args) if !check_app_ids(args.apps()) => ExitCode::FAILURE,
+        Subcommands::Get(args) if args.glob => {
+            let patterns = std::slice::from_ref(&args.id);
+            match expand_ids(proxy, &args.table, patterns, &args.matching).await {
+                Some(ids) => {
+                    let apps = args.apps();
+                    get_permissions(proxy, &args.table, &ids, &apps, true, args.by_app, format)
+                        .await
+                }
+                None => ExitCode::FAILURE,
+            }
+        }
+        Subcommands::Explain(args) => tables::explain(args.table.as_deref(), format),
+        Subcommands::ExportCsv(args) => csv::export(proxy, &args.table, &args.file).await,
+        Subcommands::Get(args) => match args.apps().as_slice() {
+            [app] if !args.by_app => {
+                let suggest = !args.no_suggest;
+                get_permission(proxy, &args.table, &args.id, app, suggest, format).await
+            }
+            apps => {
+                let ids = std::slice::from_ref(&args.id);
+                get_permissions(proxy, &args.table, ids, apps, false, args.by_app, format).await
+            }
+        },
+        Subcommands::Grep(args) => grep_permission(proxy, args, format).await,
+        Subcommands::ImportCsv(args) => {
+            csv::import(proxy, &args.table, &args.file, args.create).await
+        }
+        Subcommands::Inhibit(args) => {
+            let app = args.app.as_deref();
+            inhibit::inhibit(proxy, args.action, app, &args.flags, format).await
+        }
+        Subcommands::List(args) => list_resources(proxy, args, format).await,
+        Subcommands::Location(args) => {
+            location::location(proxy, args.action, &args.app, args.accuracy, format).await
+        }
+        Subcommands::Lookup(args) if args.ids.len() == 1 => {
+            lookup_resource(proxy, args, format).await
+        }
+        Subcommands::Lookup(args) => lookup_resources(proxy, args, format).await,
+        Subcommands::Matrix(args) => {
+            let MatrixArgs {
+                apps,
+                compact,
+                csv,
+                table,
+            } = args;
+            matrix::matrix(proxy, table, apps, *compact, *csv, format).await
+        }
+        Subcommands::Merge(args) => merge_apps(proxy, args).await,
+        Subcommands::Migrate(args) => {
+            let mapping = match migrate::load_mapping(&args.mapping) {
+                Ok(mapping) => mapping,
+                Err(e) => {
+                    fail!(e, "failed to load mapping: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            let mut tables = known_tables();
+            // --dry-run would plan the rewrites of a table passed again twice
+            for table in &args.tables {
+                if !tables.contains(table) {
+                    tables.push(table.clone());
+                }
+            }
+            migrate::migrate(proxy, &tables, &mapping, args.dry_run, args.overwrite).await
+        }
+        Subcommands::Move(args) => move_resource(proxy, args).await,
+        Subcommands::Notifications(args) => {
+            notifications::notifications(proxy, args.action, &args.app, format).await
+        }
+        Subcommands::PurgeApp(args) => purge_app(proxy, args).await,
+        Subcommands::MigrateApp(args) => {
+            let tables = if args.tables.is_empty() {
+                known_tables()
+            } else {
+                args.tables.clone()
+            };
+            let mapping = [(args.old_app.clone(), args.new_app.clone())].into();
+            migrate::migrate(proxy, &tables, &mapping, args.dry_run, args.overwrite).await
+        }
+        Subcommands::RenameApp(args) => rename_app(proxy, args).await,
+        Subcommands::Repl => {
+            fail!(ErrorKind::InvalidInput, "already in repl");
+            ExitCode::FAILURE
+        }
+        Subcommands::Restore(args) => restore_snapshot(proxy, args).await,
+        Subcommands::Revoke(args) => revoke_permission(proxy, args, format).await,
+        Subcommands::Screenshot(args) => {
+            screenshot::screenshot(proxy, args.action, args.app.as_deref(), format).await
+        }
+        Subcommands::Search(args) => search::search(proxy, args, format).await,
+        Subcommands::Set(args) => set_permission(proxy, args, format).await,
+        Subcommands::Show(args) => {
+            let mut tables = known_tables();
+            for table in &args.tables {
+                if !tables.contains(table) {
+                    tables.push(table.clone());
+                }
+            }
+            show::show(proxy, &tables, &args.app, args.with_data, args.all, format).await
+        }
+        Subcommands::Snapshot(args) => {
+            write_snapshot(proxy, args, cli.output.as_ref(), cli.mkdir).await
+        }
+        Subcommands::Stats(args) => {
+            let implicit = args.tables.is_empty();
+            let tables = if implicit {
+                known_tables()
+            } else {
+                args.tables.clone()
+            };
+            stats::stats(proxy, &tables, implicit, args.top, format).await
+        }
+        Subcommands::Tables(args) => tables::tables(args.probe.then_some(proxy), format).await,
+        Subcommands::Wallpaper(args) => {
+            wallpaper::wallpaper(proxy, args.action, args.app.as_deref(), format).await
+        }
+        Subcommands::Watch(args) => {
+            let (table, exec) = (args.table.as_deref(), args.exec.as_deref());
+            watch::watch(proxy, table, exec, args.max_reconnect, format).await
+        }
+        Subcommands::Where(args) => where_app(proxy, args, format).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn app_id_syntax() {
+        let long = format!("org.example.{}", "a".repeat(243));
+        let too_long = format!("org.example.{}", "a".repeat(244));
+        let cases = [
+            ("org.gnome.Maps", true),
+            ("org.gnome.Maps.Devel", true),
+            ("org.example.my-app", true),
+            ("org._1.App_2", true),
+            (long.as_str(), true),
+            ("org", false),
+            ("org.gnome", false),
+            ("org.gnome.", false),
+            ("org..Maps", false),
+            (".org.gnome.Maps", false),
+            ("org.1gnome.Maps", false),
+            ("org.gnome.2Maps", false),
+            ("org.my-company.App", false),
+            ("my-org.gnome.Maps", false),
+            (too_long.as_str(), false),
+            ("org.gnome.Mäps", false),
+            ("org.gnome.地図", false),
+            ("org.gnome.Maps App", false),
+            ("org/gnome/Maps", false),
+        ];
+        for (app, valid) in cases {
+            assert_eq!(is_valid_app_id(app), valid, "{app}");
+        }
+    }
+
+    #[test]
+    fn check_app_ids_accepts_the_default_entry() {
+        assert!(check_app_ids(["", "org.gnome.Maps"]));
+        assert!(!check_app_ids(["org.gnome.Maps", "org.gnome"]));
+        assert!(!check_app_ids(["yes"]));
+    }
+
+    fn set_args(args: &[&str]) -> SetArgs {
+        let cli = Cli::try_parse_from(["xdp-perm", "set"].iter().chain(args)).unwrap();
+        match cli.command {
+            Subcommands::Set(args) => args,
+            command => panic!("parsed {command:?}"),
+        }
+    }
+
+    #[test]
+    fn dedup_keeps_the_first_occurrence() {
+        let (unique, repeats) = dedup_permissions(&strings(&["b", "a", "b", "c", "a", "b"]));
+        assert_eq!(unique, strings(&["b", "a", "c"]));
+        assert_eq!(repeats, strings(&["b", "a", "b"]));
+        assert_eq!(dedup_permissions(&[]), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn normalize_dedups_unless_verbatim() {
+        let requested = strings(&["yes", "ask", "yes"]);
+        let args = set_args(&["notifications", "notification", "org.a.A"]);
+        assert_eq!(
+            normalize_permissions(&args, &requested),
+            strings(&["yes", "ask"])
+        );
+        let args = set_args(&["--verbatim", "notifications", "notification", "org.a.A"]);
+        assert_eq!(normalize_permissions(&args, &requested), requested);
+    }
+
+    #[test]
+    fn normalize_case_only_for_lowercase_tables() {
+        let requested = strings(&["Yes", "YES", "no"]);
+        let args = set_args(&["--normalize-case", "notifications", "n", "org.a.A"]);
+        assert_eq!(
+            normalize_permissions(&args, &requested),
+            strings(&["yes", "no"])
+        );
+        let args = set_args(&["--normalize-case", "org.example.custom", "n", "org.a.A"]);
+        assert_eq!(normalize_permissions(&args, &requested), requested);
+        let args = set_args(&["notifications", "n", "org.a.A"]);
+        assert_eq!(normalize_permissions(&args, &requested), requested);
+    }
+
+    fn sorted(sort: ListSort, ids: &[&str]) -> Vec<String> {
+        let mut ids = strings(ids);
+        sort.sort(&mut ids);
+        ids
+    }
+
+    #[test]
+    fn ids_sort_byte_by_byte() {
+        let ids = ["b", "a", "B", "é", "z", "10", "9", "a-b", "a_b", "ab"];
+        assert_eq!(
+            sorted(ListSort::Id, &ids),
+            strings(&["10", "9", "B", "a", "a-b", "a_b", "ab", "b", "z", "é"])
+        );
+    }
+
+    #[test]
+    fn numeric_ids_sort_by_value_first() {
+        let ids = ["b", "10", "a", "9", "010", "-1", "18446744073709551616"];
+        assert_eq!(
+            sorted(ListSort::Numeric, &ids),
+            strings(&["9", "010", "10", "-1", "18446744073709551616", "a", "b"])
+        );
+    }
+
+    #[test]
+    fn no_sort_keeps_the_store_order() {
+        let ids = ["b", "a", "c"];
+        assert_eq!(sorted(ListSort::None, &ids), strings(&ids));
+    }
+
+    #[test]
+    fn entries_sort_by_app_or_count() {
+        let entries = HashMap::from([
+            ("org.b.B".to_string(), strings(&["yes"])),
+            ("org.a.A".to_string(), strings(&["yes"])),
+            ("org.c.C".to_string(), strings(&["yes", "ask"])),
+            ("Org.d.D".to_string(), strings(&[])),
+        ]);
+        let order = |sort: SortBy| {
+            let mut apps: Vec<_> = entries.iter().collect();
+            sort.sort(&mut apps);
+            apps.into_iter()
+                .map(|(app, _)| app.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            order(SortBy::App),
+            ["Org.d.D", "org.a.A", "org.b.B", "org.c.C"]
+        );
+        assert_eq!(
+            order(SortBy::Count),
+            ["org.c.C", "org.a.A", "org.b.B", "Org.d.D"]
+        );
+    }
+
+    fn command(args: &[&str]) -> Result<Subcommands, clap::Error> {
+        Cli::try_parse_from(["xdp-perm"].iter().chain(args)).map(|cli| cli.command)
+    }
+
+    #[test]
+    fn aliases_parse_like_their_subcommand() {
+        let cases: &[(&str, &str, &[&str])] = &[
+            ("reset", "clear-table", &["-y", "devices"]),
+            ("rm", "delete", &["--app", "org.a.A", "devices", "camera"]),
+            ("g", "get", &["devices", "camera", "org.a.A"]),
+            ("ls", "list", &["--sort", "numeric", "devices"]),
+            ("lu", "lookup", &["devices", "camera", "speakers"]),
+            ("delete-app", "purge-app", &["devices", "org.a.A"]),
+            ("s", "set", &["devices", "camera", "org.a.A", "yes"]),
+            ("dump", "snapshot", &["devices", "devices.json"]),
+        ];
+        for (alias, name, rest) in cases {
+            let by_alias = command(&[&[*alias], *rest].concat()).unwrap();
+            let by_name = command(&[&[*name], *rest].concat()).unwrap();
+            assert_eq!(format!("{by_alias:?}"), format!("{by_name:?}"), "{alias}");
+        }
+        assert!(matches!(
+            command(&["ls", "devices"]),
+            Ok(Subcommands::List(_))
+        ));
+        assert!(matches!(
+            command(&["rm", "devices", "camera"]),
+            Ok(Subcommands::Delete(_))
+        ));
+    }
+
+    #[test]
+    fn subcommands_are_not_abbreviated() {
+        for abbreviation in ["l", "se", "del", "looku"] {
+            let e = command(&[abbreviation, "devices"]).unwrap_err();
+            assert_eq!(
+                e.kind(),
+                clap::error::ErrorKind::InvalidSubcommand,
+                "{abbreviation}"
+            );
+        }
+    }
+
+    #[test]
+    fn tables_wrap_by_display_width() {
+        use unicode_width::UnicodeWidthStr;
+
+        let app = "org.例え.カメラアプリ";
+        let permissions = strings(&["はい", "cafe\u{301}", "ask"]);
+        let mut table = table_of_width(24);
+        table.set_header(["App", "Permissions"]);
+        table.add_row([Cell::new(app), permissions_cell(&permissions)]);
+        let text = table.to_string();
+
+        let lines: Vec<&str> = text.lines().collect();
+        for line in &lines {
+            assert_eq!(line.width(), 24, "{text}");
+        }
+        // the wrapped pieces of every cell put back together
+        let body = lines
+            .iter()
+            .skip_while(|line| !line.starts_with("+="))
+            .skip(1);
+        let mut cells = [String::new(), String::new()];
+        for line in body.filter(|line| line.starts_with('|')) {
+            for (cell, piece) in cells.iter_mut().zip(line.split('|').skip(1)) {
+                cell.push_str(piece.trim());
+            }
+        }
+        assert_eq!(cells, [app.to_string(), permissions.join(",")], "{text}");
+    }
+
+    #[test]
+    fn the_last_of_opposite_flags_wins() {
+        let base = ["devices", "camera", "org.a.A", "yes"];
+        let set = |flags: &[&str]| set_args(&[flags, &base].concat());
+        assert!(!set(&[]).if_changed() && !set(&[]).strict());
+        assert!(set(&["--if-changed"]).if_changed());
+        assert!(!set(&["--if-changed", "--always-write"]).if_changed());
+        assert!(set(&["--always-write", "--if-changed"]).if_changed());
+        assert!(!set(&["--strict", "--no-strict"]).strict());
+        assert!(set(&["--no-strict", "--strict"]).strict());
+    }
+
+    #[test]
+    fn rename_onto_itself_is_caught() {
+        let rename =
+            |old: &str, new: &str| match command(&["rename-app", "devices", "camera", old, new]) {
+                Ok(Subcommands::RenameApp(args)) => args,
+                command => panic!("parsed {command:?}"),
+            };
+        assert!(rename("org.a.A", "org.a.A").onto_itself());
+        assert!(!rename("org.a.A", "org.b.B").onto_itself());
+        // application IDs are case sensitive
+        assert!(!rename("org.a.A", "org.a.a").onto_itself());
+    }
+
+    #[test]
+    fn delete_rejects_a_positional_application() {
+        let delete = |args: &[&str]| match command(&[&["delete"], args].concat()) {
+            Ok(Subcommands::Delete(args)) => args,
+            command => panic!("parsed {command:?}"),
+        };
+        let args = delete(&["devices", "camera", "org.a.A"]);
+        assert!(args.resolve().is_err());
+        let args = delete(&["devices", "a", "b.c"]);
+        assert_eq!(args.resolve(), Ok((&strings(&["a", "b.c"])[..], vec![])));
+        let args = delete(&["devices", "a", "b", "org.a.A"]);
+        assert_eq!(args.resolve().unwrap().0, strings(&["a", "b", "org.a.A"]));
+        let args = delete(&["--app", "org.a.A", "devices", "a", "b.c.d"]);
+        assert_eq!(
+            args.resolve(),
+            Ok((&strings(&["a", "b.c.d"])[..], vec!["org.a.A"]))
+        );
+    }
+
+    #[test]
+    fn append_to_an_empty_entry() {
+        assert_eq!(
+            merge_permissions(&[], &strings(&["yes", "ask"]), false),
+            strings(&["yes", "ask"])
+        );
+    }
+
+    #[test]
+    fn append_adds_every_value_once() {
+        assert_eq!(
+            merge_permissions(&[], &strings(&["yes", "yes", "ask", "yes"]), false),
+            strings(&["yes", "ask"])
+        );
+        assert_eq!(
+            merge_permissions(&strings(&["ask"]), &strings(&["yes", "ask"]), false),
+            strings(&["ask", "yes"])
+        );
+    }
+
+    #[test]
+    fn append_keeps_the_current_order_first() {
+        assert_eq!(
+            merge_permissions(&strings(&["c", "a"]), &strings(&["b", "a", "d"]), false),
+            strings(&["c", "a", "b", "d"])
+        );
+    }
+
+    #[test]
+    fn remove_takes_out_every_occurrence() {
+        assert_eq!(
+            merge_permissions(&strings(&["a", "b", "a", "c"]), &strings(&["a"]), true),
+            strings(&["b", "c"])
+        );
+    }
+
+    #[test]
+    fn remove_ignores_absent_values() {
+        assert_eq!(
+            merge_permissions(&strings(&["a", "b"]), &strings(&["z"]), true),
+            strings(&["a", "b"])
+        );
+        assert_eq!(merge_permissions(&[], &strings(&["z"]), true), strings(&[]));
+    }
+
+    #[test]
+    fn lookups_json_keeps_argument_order() {
+        let response: LookupResponse = (
+            HashMap::from([("org.a.A".to_string(), vec!["yes".to_string()])]),
+            OwnedValue::from(0u8),
+        );
+        let json = LookupsJson(vec![
+            ("zzz", None),
+            ("notification", Some(LookupJson::new(&response, false))),
+            ("aaa", None),
+        ]);
+        assert_eq!(
+            serde_json::to_string(&json).unwrap(),
+            r#"{"zzz":null,"notification":{"permissions":{"org.a.A":["yes"]},"data":null},"aaa":null}"#
+        );
+    }
 }
@@ -0,0 +1,122 @@
+//! A table pivoted into resources by applications.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::store::Store;
+use comfy_table::Cell;
+
+use crate::{
+    Format, cell_text, csv, is_not_found, lookup_all, new_table, permission_color, print_json,
+};
+
+#[derive(Serialize, Debug)]
+struct Matrix {
+    apps: Vec<String>,
+    /// Resource ID to the permissions of every application, `None` for no entry
+    resources: BTreeMap<String, Vec<Option<Vec<String>>>>,
+}
+
+/// The cell of an entry, with `compact` turning yes/no/ask into glyphs.
+fn cell(permissions: &Option<Vec<String>>, compact: bool) -> String {
+    let Some(permissions) = permissions else {
+        return String::new();
+    };
+    let values: Vec<&str> = permissions
+        .iter()
+        .map(|permission| match permission.as_str() {
+            "yes" if compact => "✓",
+            "no" if compact => "✗",
+            "ask" if compact => "?",
+            permission => permission,
+        })
+        .collect();
+    values.join(",")
+}
+
+pub async fn matrix(
+    proxy: &Store<'_>,
+    table: &str,
+    apps: &[String],
+    compact: bool,
+    csv: bool,
+    format: Format,
+) -> ExitCode {
+    let ids = match proxy.list(table).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            fail!(e, "failed to list permissions: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    let mut entries = BTreeMap::new();
+    for (id, result) in lookup_all(proxy, table, &ids).await {
+        match result {
+            Ok((permissions, _)) => {
+                entries.insert(id, permissions);
+            }
+            // the resource was deleted while scanning
+            Err(e) if is_not_found(&e) => {}
+            Err(e) => {
+                fail!(e, "failed to lookup {id}: {e}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let columns: Vec<String> = if apps.is_empty() {
+        let all: BTreeSet<&String> = entries.values().flat_map(|apps| apps.keys()).collect();
+        all.into_iter().cloned().collect()
+    } else {
+        apps.to_vec()
+    };
+    let matrix = Matrix {
+        resources: entries
+            .into_iter()
+            .map(|(id, mut permissions)| {
+                let row = columns.iter().map(|app| permissions.remove(app)).collect();
+                (id, row)
+            })
+            .collect(),
+        apps: columns,
+    };
+
+    if csv {
+        let header: Vec<String> = std::iter::once("Resource ID")
+            .chain(matrix.apps.iter().map(String::as_str))
+            .map(csv::field)
+            .collect();
+        println!("{}", header.join(","));
+        for (id, row) in &matrix.resources {
+            let fields: Vec<String> = std::iter::once(csv::field(id))
+                .chain(row.iter().map(|entry| csv::field(&cell(entry, compact))))
+                .collect();
+            println!("{}", fields.join(","));
+        }
+        return code;
+    }
+    if format == Format::Json {
+        print_json(&matrix);
+        return code;
+    }
+
+    let mut table = new_table();
+    table.set_header(std::iter::once("Resource ID").chain(matrix.apps.iter().map(String::as_str)));
+    for (id, row) in &matrix.resources {
+        let cells = row.iter().map(|entry| {
+            let cell = Cell::new(cell_text(&cell(entry, compact)));
+            match entry.as_deref().and_then(permission_color) {
+                Some(color) => cell.fg(color),
+                None => cell,
+            }
+        });
+        table.add_row(std::iter::once(Cell::new(cell_text(id))).chain(cells));
+    }
+
+    println!("{table}");
+    code
+}
@@ -0,0 +1,160 @@
+//! Moving permissions from old application IDs to new ones across tables.
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+use crate::store::Store;
+use crate::{is_not_found, lookup_all, new_table};
+
+/// What happened to the entries of a single mapping.
+#[derive(Default, Debug)]
+struct Counts {
+    migrated: usize,
+    conflicts: usize,
+    failed: usize,
+}
+
+/// Load a mapping file of the form `"org.old.App" = "org.new.App"`.
+pub fn load_mapping(path: &str) -> Result<BTreeMap<String, String>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    parse_mapping(&content).map_err(|e| format!("{path}: {e}"))
+}
+
+/// The mapping in `content`, where an ID mapped to itself is an error:
+/// moving it would write its entries back and then delete them.
+fn parse_mapping(content: &str) -> Result<BTreeMap<String, String>, String> {
+    let mapping: BTreeMap<String, String> = toml::from_str(content).map_err(|e| e.to_string())?;
+    if let Some((old, _)) = mapping.iter().find(|(old, new)| old == new) {
+        return Err(format!("{old} is mapped to itself"));
+    }
+    Ok(mapping)
+}
+
+/// Rewrite every entry of an old application ID to its new ID in `tables`.
+pub async fn migrate(
+    proxy: &Store<'_>,
+    tables: &[String],
+    mapping: &BTreeMap<String, String>,
+    dry_run: bool,
+    overwrite: bool,
+) -> ExitCode {
+    let mut counts: BTreeMap<&str, Counts> = mapping
+        .keys()
+        .map(|old| (old.as_str(), Counts::default()))
+        .collect();
+    let mut code = ExitCode::SUCCESS;
+
+    for table in tables {
+        let ids = match proxy.list(table).await {
+            Ok(ids) => ids,
+            Err(e) if is_not_found(&e) => continue,
+            Err(e) => {
+                fail!(e, "failed to list {table}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        for (id, result) in lookup_all(proxy, table, &ids).await {
+            let apps = match result {
+                Ok((apps, _)) => apps,
+                Err(e) if is_not_found(&e) => continue,
+                Err(e) => {
+                    fail!(e, "failed to lookup {id} in {table}: {e}");
+                    code = ExitCode::FAILURE;
+                    continue;
+                }
+            };
+
+            for (old, new) in mapping {
+                let Some(permissions) = apps.get(old) else {
+                    continue;
+                };
+                let count = counts
+                    .get_mut(old.as_str())
+                    .expect("every mapping is counted");
+
+                if let Some(existing) = apps.get(new)
+                    && existing != permissions
+                    && !overwrite
+                {
+                    status!(
+                        "{table}/{id}: conflict, {new} already has [{}], skipping",
+                        existing.join(",")
+                    );
+                    count.conflicts += 1;
+                    continue;
+                }
+
+                if dry_run {
+                    println!("{table}/{id}: would move {old} to {new}");
+                    count.migrated += 1;
+                    continue;
+                }
+
+                let result = match proxy
+                    .set_permission(table, false, &id, new, permissions)
+                    .await
+                {
+                    Ok(_) => proxy.delete_permission(table, &id, old).await,
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(_) => {
+                        status!("{table}/{id}: moved {old} to {new}");
+                        count.migrated += 1;
+                    }
+                    Err(e) => {
+                        fail!(e, "{table}/{id}: failed to move {old} to {new}: {e}");
+                        count.failed += 1;
+                        code = ExitCode::FAILURE;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut summary = new_table();
+    summary.set_header(vec![
+        "Old AppID",
+        "New AppID",
+        "Migrated",
+        "Conflicts",
+        "Failed",
+    ]);
+    for (old, count) in &counts {
+        summary.add_row(vec![
+            old.to_string(),
+            mapping[*old].clone(),
+            count.migrated.to_string(),
+            count.conflicts.to_string(),
+            count.failed.to_string(),
+        ]);
+    }
+    println!("{summary}");
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mappings_are_parsed() {
+        let mapping =
+            parse_mapping("\"org.a.A\" = \"org.b.B\"\n\"org.c.C\" = \"org.a.A\"").unwrap();
+        assert_eq!(mapping["org.a.A"], "org.b.B");
+        assert_eq!(mapping["org.c.C"], "org.a.A");
+        assert!(parse_mapping("").unwrap().is_empty());
+        assert!(parse_mapping("\"org.a.A\" = 1").is_err());
+    }
+
+    #[test]
+    fn ids_mapped_to_themselves_are_rejected() {
+        let e = parse_mapping("\"org.b.B\" = \"org.c.C\"\n\"org.a.A\" = \"org.a.A\"").unwrap_err();
+        assert_eq!(e, "org.a.A is mapped to itself");
+        // IDs are case sensitive
+        assert!(parse_mapping("\"org.a.A\" = \"org.a.a\"").is_ok());
+    }
+}
@@ -0,0 +1,83 @@
+//! Shortcuts for the notifications table, which only has a single resource.
+
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::shortcut::{Resource, check_app};
+use crate::store::Store;
+use crate::{Format, NotificationAction, print_json};
+
+const RESOURCE: Resource = Resource {
+    table: "notifications",
+    id: "notification",
+    what: "notification",
+};
+
+#[derive(Serialize, Debug)]
+struct Status<'a> {
+    app: &'a str,
+    /// `None` when the application has no entry
+    permissions: Option<Vec<String>>,
+}
+
+pub async fn notifications(
+    proxy: &Store<'_>,
+    action: NotificationAction,
+    app: &str,
+    format: Format,
+) -> ExitCode {
+    if !check_app(app) {
+        return ExitCode::FAILURE;
+    }
+
+    let permission = match action {
+        NotificationAction::Allow => "yes",
+        NotificationAction::Deny => "no",
+        NotificationAction::Reset => {
+            return match RESOURCE.reset(proxy, app).await {
+                Some(true) => {
+                    status!("{app} is back to the default notification behavior");
+                    ExitCode::SUCCESS
+                }
+                Some(false) => {
+                    status!("{app} has no notification setting to reset");
+                    ExitCode::SUCCESS
+                }
+                None => ExitCode::FAILURE,
+            };
+        }
+        NotificationAction::Status => return print_status(proxy, app, format).await,
+    };
+
+    if !RESOURCE.set(proxy, app, &[permission.to_string()]).await {
+        return ExitCode::FAILURE;
+    }
+    if permission == "yes" {
+        status!("{app} may now send notifications");
+    } else {
+        status!("{app} may no longer send notifications");
+    }
+    ExitCode::SUCCESS
+}
+
+async fn print_status(proxy: &Store<'_>, app: &str, format: Format) -> ExitCode {
+    let Some(permissions) = RESOURCE.permissions(proxy, app).await else {
+        return ExitCode::FAILURE;
+    };
+
+    if format == Format::Json {
+        print_json(&Status { app, permissions });
+        return ExitCode::SUCCESS;
+    }
+    match permissions.as_deref() {
+        None => println!("{app} has no notification setting, the portal default applies"),
+        Some([value]) if value == "yes" => println!("{app} is allowed to send notifications"),
+        Some([value]) if value == "no" => println!("{app} is not allowed to send notifications"),
+        Some(values) => println!(
+            "{app} has the unusual notification setting {}",
+            values.join(",")
+        ),
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,172 @@
+//! Sending the printed results to a file instead of stdout, see `--output`,
+//! or through a pager when they do not fit the terminal.
+
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+/// Whether stdout currently points at an `--output` file.
+static REDIRECTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether informational messages should move to stderr, so that only the
+/// results end up in the output file.
+pub fn redirected() -> bool {
+    REDIRECTED.load(Ordering::Relaxed)
+}
+
+/// Create the missing parent directories of `path`.
+pub fn create_parent(path: &str) -> io::Result<()> {
+    match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => std::fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
+
+/// Point stdout at `fd`, returning the descriptor it used before.
+fn swap_stdout(fd: RawFd) -> io::Result<OwnedFd> {
+    io::stdout().flush()?;
+    // SAFETY: plain descriptor calls, the duplicate is owned right away
+    let stdout = unsafe {
+        let saved = libc::dup(libc::STDOUT_FILENO);
+        if saved < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        OwnedFd::from_raw_fd(saved)
+    };
+    // SAFETY: both descriptors are open for the duration of the call
+    if unsafe { libc::dup2(fd, libc::STDOUT_FILENO) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stdout)
+}
+
+/// Point stdout back at the descriptor saved by [`swap_stdout`].
+fn restore_stdout(stdout: &OwnedFd) {
+    let _ = io::stdout().flush();
+    // SAFETY: the saved descriptor is owned by the caller and still open
+    unsafe { libc::dup2(stdout.as_raw_fd(), libc::STDOUT_FILENO) };
+}
+
+/// The columns and rows of the terminal on stdout, if it is one.
+pub fn terminal_size() -> Option<(u16, u16)> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    // SAFETY: TIOCGWINSZ only fills in the winsize it is handed
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } < 0 {
+        return None;
+    }
+    (size.ws_col > 0 && size.ws_row > 0).then_some((size.ws_col, size.ws_row))
+}
+
+/// Points stdout at a file until dropped, diagnostics stay on stderr.
+pub struct Redirect {
+    stdout: OwnedFd,
+}
+
+impl Redirect {
+    /// Truncate `path` and send stdout there, creating its parent directory
+    /// first with `mkdir`.
+    pub fn to(path: &str, mkdir: bool) -> io::Result<Self> {
+        if mkdir {
+            create_parent(path)?;
+        }
+        let file = File::create(path)?;
+        let stdout = swap_stdout(file.as_raw_fd())?;
+        REDIRECTED.store(true, Ordering::Relaxed);
+        Ok(Self { stdout })
+    }
+}
+
+impl Drop for Redirect {
+    fn drop(&mut self) {
+        restore_stdout(&self.stdout);
+        REDIRECTED.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Collects stdout until dropped, then shows it through `$PAGER` if it has
+/// more lines than the terminal, or prints it as is.
+pub struct Pager {
+    stdout: OwnedFd,
+    rows: usize,
+    reader: Option<JoinHandle<Vec<u8>>>,
+}
+
+impl Pager {
+    /// Start collecting stdout, `None` unless it is a terminal.
+    pub fn start() -> Option<Self> {
+        let (_, rows) = terminal_size()?;
+        let mut fds = [0; 2];
+        // SAFETY: pipe only fills in the two descriptors, owned right away
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+            return None;
+        }
+        let (read, write) = unsafe { (File::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+
+        // read on the side, a full pipe would block the command otherwise
+        let reader = std::thread::spawn(move || {
+            let mut read = read;
+            let mut buffer = Vec::new();
+            let _ = read.read_to_end(&mut buffer);
+            buffer
+        });
+        let stdout = swap_stdout(write.as_raw_fd()).ok()?;
+        Some(Self {
+            stdout,
+            rows: rows.into(),
+            reader: Some(reader),
+        })
+    }
+}
+
+/// Write `text` to the first pager that starts, `$PAGER` then less and more.
+fn page(text: &[u8]) -> bool {
+    let configured = std::env::var("PAGER")
+        .ok()
+        .and_then(|pager| shlex::split(&pager));
+    let fallbacks = [vec!["less".to_string()], vec!["more".to_string()]];
+    for command in configured.iter().chain(&fallbacks) {
+        let Some((program, args)) = command.split_first() else {
+            continue;
+        };
+        let mut pager = Command::new(program);
+        pager.args(args).stdin(Stdio::piped());
+        if std::env::var_os("LESS").is_none() {
+            // keep the colors of the tables
+            pager.env("LESS", "R");
+        }
+        let Ok(mut child) = pager.spawn() else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            // the user may quit before reading everything
+            let _ = stdin.write_all(text);
+        }
+        let _ = child.wait();
+        return true;
+    }
+    false
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        // the last write end goes away here, which ends the reader
+        restore_stdout(&self.stdout);
+        let Some(text) = self.reader.take().and_then(|reader| reader.join().ok()) else {
+            return;
+        };
+        let lines = text.iter().filter(|&&byte| byte == b'\n').count();
+        if lines >= self.rows && page(&text) {
+            return;
+        }
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(&text);
+        let _ = stdout.flush();
+    }
+}
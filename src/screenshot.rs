@@ -0,0 +1,95 @@
+//! Shortcuts for the screenshot table, which only has a single resource.
+
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::error::ErrorKind;
+use crate::shortcut::{Resource, check_app};
+use crate::store::Store;
+use crate::{Format, ScreenshotAction, print_json};
+
+const RESOURCE: Resource = Resource {
+    table: "screenshot",
+    id: "screenshot",
+    what: "screenshot",
+};
+
+#[derive(Serialize, Debug)]
+struct Status<'a> {
+    app: &'a str,
+    /// `None` when the application has no entry
+    permissions: Option<Vec<String>>,
+}
+
+pub async fn screenshot(
+    proxy: &Store<'_>,
+    action: ScreenshotAction,
+    app: Option<&str>,
+    format: Format,
+) -> ExitCode {
+    if action == ScreenshotAction::List {
+        return RESOURCE.list(proxy, "Screenshot", format).await;
+    }
+    let Some(app) = app else {
+        fail!(ErrorKind::InvalidInput, "expected <APP>");
+        return ExitCode::FAILURE;
+    };
+    if !check_app(app) {
+        return ExitCode::FAILURE;
+    }
+
+    let permission = match action {
+        ScreenshotAction::Allow => "yes",
+        ScreenshotAction::Deny => "no",
+        ScreenshotAction::Reset => {
+            return match RESOURCE.reset(proxy, app).await {
+                Some(true) => {
+                    status!("the portal will ask again before {app} takes a screenshot");
+                    ExitCode::SUCCESS
+                }
+                Some(false) => {
+                    status!("{app} has no screenshot setting to reset");
+                    ExitCode::SUCCESS
+                }
+                None => ExitCode::FAILURE,
+            };
+        }
+        ScreenshotAction::Status => return print_status(proxy, app, format).await,
+        ScreenshotAction::List => unreachable!("handled above"),
+    };
+    if !RESOURCE.set(proxy, app, &[permission.to_string()]).await {
+        return ExitCode::FAILURE;
+    }
+    if permission == "yes" {
+        status!("{app} may now take screenshots without the dialog");
+    } else {
+        status!("{app} may no longer take screenshots");
+    }
+    ExitCode::SUCCESS
+}
+
+async fn print_status(proxy: &Store<'_>, app: &str, format: Format) -> ExitCode {
+    let Some(permissions) = RESOURCE.permissions(proxy, app).await else {
+        return ExitCode::FAILURE;
+    };
+
+    if format == Format::Json {
+        print_json(&Status { app, permissions });
+        return ExitCode::SUCCESS;
+    }
+    match permissions.as_deref() {
+        Some([value]) if value == "yes" => {
+            println!("{app} takes screenshots without asking, the portal silently allows them")
+        }
+        Some([value]) if value == "no" => {
+            println!("{app} may not take screenshots, the portal silently denies them")
+        }
+        None => println!("{app} has no screenshot setting, the portal will prompt"),
+        Some(values) => println!(
+            "{app} has the unusual screenshot setting {}, the portal will prompt",
+            values.join(",")
+        ),
+    }
+    ExitCode::SUCCESS
+}
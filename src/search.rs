@@ -0,0 +1,138 @@
+//! Scanning tables for entries matching a set of filters.
+
+use std::process::ExitCode;
+
+use futures::StreamExt;
+
+use crate::filter::Glob;
+use crate::store::Store;
+use crate::{EntryRow, Format, SearchArgs, cell_text, is_not_found, known_tables, lookup_stream};
+
+/// The filters of a search, which all have to match.
+struct Filters<'a> {
+    app: Option<Glob>,
+    id: Option<Glob>,
+    args: &'a SearchArgs,
+}
+
+impl Filters<'_> {
+    fn matches_permissions(&self, permissions: &[String]) -> bool {
+        let Some(expected) = &self.args.permission else {
+            return true;
+        };
+        permissions
+            .iter()
+            .any(|permission| self.args.matching.matches(expected, permission))
+    }
+}
+
+/// Print a match as soon as it is found instead of once the scan is over.
+fn print_row(row: &EntryRow, format: Format) {
+    let permissions = row.permissions.join(",");
+    match format {
+        Format::Json => match serde_json::to_string(row) {
+            Ok(json) => println!("{json}"),
+            Err(e) => fail!(e, "failed to serialize output: {e}"),
+        },
+        Format::Markdown => {
+            let mut cells = Vec::with_capacity(4);
+            cells.extend(row.table.as_deref().map(cell_text));
+            cells.extend([
+                cell_text(&row.id),
+                cell_text(&row.app),
+                cell_text(&permissions),
+            ]);
+            println!("| {} |", cells.join(" | "));
+        }
+        Format::Table => match &row.table {
+            Some(table) => println!("{table} {} {}: {permissions}", row.id, row.app),
+            None => println!("{} {}: {permissions}", row.id, row.app),
+        },
+    }
+}
+
+pub async fn search(proxy: &Store<'_>, args: &SearchArgs, format: Format) -> ExitCode {
+    let glob = |pattern: &Option<String>| {
+        pattern
+            .as_deref()
+            .map(|pattern| args.matching.glob(pattern))
+            .transpose()
+    };
+    let filters = match (glob(&args.app), glob(&args.id)) {
+        (Ok(app), Ok(id)) => Filters { app, id, args },
+        (Err(e), _) | (_, Err(e)) => {
+            fail!(e, "{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let tables = match &args.table {
+        Some(table) => vec![table.clone()],
+        None => known_tables(),
+    };
+
+    if format == Format::Markdown {
+        if args.all_tables {
+            println!("| Table | Resource ID | AppID | Permissions |");
+            println!("|-------|-------------|-------|-------------|");
+        } else {
+            println!("| Resource ID | AppID | Permissions |");
+            println!("|-------------|-------|-------------|");
+        }
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    for table in &tables {
+        let mut ids = match proxy.list(table).await {
+            Ok(ids) => ids,
+            // the table has never been created on this system
+            Err(e) if is_not_found(&e) && args.all_tables => continue,
+            Err(e) => {
+                fail!(e, "failed to list {table}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        if let Some(filter) = &filters.id {
+            ids.retain(|id| filter.matches(id));
+        }
+
+        let mut lookups = std::pin::pin!(lookup_stream(proxy, table, &ids));
+        while let Some((id, result)) = lookups.next().await {
+            let (apps, _) = match result {
+                Ok(response) => response,
+                // the resource was deleted while scanning
+                Err(e) if is_not_found(&e) => continue,
+                Err(e) => {
+                    fail!(e, "failed to lookup {id} in {table}: {e}");
+                    code = ExitCode::FAILURE;
+                    continue;
+                }
+            };
+
+            let mut apps: Vec<_> = apps.into_iter().collect();
+            apps.sort();
+            for (app, permissions) in apps {
+                if filters
+                    .app
+                    .as_ref()
+                    .is_some_and(|filter| !filter.matches(&app))
+                {
+                    continue;
+                }
+                if !filters.matches_permissions(&permissions) {
+                    continue;
+                }
+                let row = EntryRow {
+                    table: args.all_tables.then(|| table.clone()),
+                    id: id.clone(),
+                    app,
+                    permissions,
+                };
+                print_row(&row, format);
+            }
+        }
+    }
+
+    code
+}
@@ -0,0 +1,119 @@
+//! The plumbing the shortcuts for well-known portal resources share, such as
+//! `background`, `notifications` and `wallpaper`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::process::ExitCode;
+use std::sync::atomic::Ordering;
+
+use zbus::zvariant::OwnedValue;
+
+use crate::error::ErrorKind;
+use crate::store::Store;
+use crate::{
+    Format, NO_HEADER, cell_text, check_app_ids, is_not_found, new_table, permissions_cell,
+    print_json,
+};
+
+/// A resource the portal keeps in a fixed table under a fixed ID.
+pub struct Resource {
+    pub table: &'static str,
+    pub id: &'static str,
+    /// What the resource is about, as it reads in messages
+    pub what: &'static str,
+}
+
+impl Resource {
+    /// The entries and data of the resource, empty when the portal never
+    /// created it. `None` once the failure is reported.
+    pub async fn entries(
+        &self,
+        proxy: &Store<'_>,
+    ) -> Option<(HashMap<String, Vec<String>>, Option<OwnedValue>)> {
+        match proxy.lookup(self.table, self.id).await {
+            Ok((apps, data)) => Some((apps, Some(data))),
+            Err(e) if is_not_found(&e) => Some((HashMap::new(), None)),
+            Err(e) => {
+                fail!(e, "failed to lookup the {} permissions: {e}", self.what);
+                None
+            }
+        }
+    }
+
+    /// The permissions of `app`, `Some(None)` for no entry.
+    pub async fn permissions(&self, proxy: &Store<'_>, app: &str) -> Option<Option<Vec<String>>> {
+        let (mut apps, _) = self.entries(proxy).await?;
+        Some(apps.remove(app))
+    }
+
+    /// Store `permissions` for `app`, reporting a failure.
+    pub async fn set(&self, proxy: &Store<'_>, app: &str, permissions: &[String]) -> bool {
+        // the table does not exist before the portal first asks
+        match proxy
+            .set_permission(self.table, true, self.id, app, permissions)
+            .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                fail!(
+                    e,
+                    "failed to set the {} permission of {app}: {e}",
+                    self.what
+                );
+                false
+            }
+        }
+    }
+
+    /// Delete the entry of `app`, `Some(false)` when it had none and `None`
+    /// once the failure is reported.
+    pub async fn reset(&self, proxy: &Store<'_>, app: &str) -> Option<bool> {
+        match proxy.delete_permission(self.table, self.id, app).await {
+            Ok(_) => Some(true),
+            Err(e) if is_not_found(&e) => Some(false),
+            Err(e) => {
+                fail!(
+                    e,
+                    "failed to reset the {} permission of {app}: {e}",
+                    self.what
+                );
+                None
+            }
+        }
+    }
+
+    /// Print every application with its permissions under `header`.
+    pub async fn list(&self, proxy: &Store<'_>, header: &str, format: Format) -> ExitCode {
+        let Some((apps, _)) = self.entries(proxy).await else {
+            return ExitCode::FAILURE;
+        };
+        let apps: BTreeMap<String, Vec<String>> = apps.into_iter().collect();
+
+        if format == Format::Json {
+            print_json(&apps);
+            return ExitCode::SUCCESS;
+        }
+
+        let mut table = new_table();
+        if !NO_HEADER.load(Ordering::Relaxed) {
+            table.set_header(vec!["AppID", header]);
+        }
+        for (app, permissions) in &apps {
+            table.add_row(vec![cell_text(app).into(), permissions_cell(permissions)]);
+        }
+
+        println!("{table}");
+        ExitCode::SUCCESS
+    }
+}
+
+/// Whether `app` is an application ID, reporting it when it is not.
+pub fn check_app(app: &str) -> bool {
+    if app.is_empty() {
+        fail!(
+            ErrorKind::InvalidInput,
+            "expected an application ID such as org.gnome.Maps"
+        );
+        return false;
+    }
+    check_app_ids([app])
+}
@@ -0,0 +1,83 @@
+//! Everything a single application has been granted, across tables.
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+use serde::Serialize;
+use zbus::zvariant::OwnedValue;
+
+use crate::store::Store;
+use crate::{Format, is_not_found, lookup_all, print_json};
+
+#[derive(Serialize, Debug)]
+struct Entry {
+    permissions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<OwnedValue>,
+}
+
+/// Print the entries of `app` grouped by table and resource.
+///
+/// Tables without an entry are left out unless `all` is set.
+pub async fn show(
+    proxy: &Store<'_>,
+    tables: &[String],
+    app: &str,
+    with_data: bool,
+    all: bool,
+    format: Format,
+) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    let mut report: BTreeMap<&str, BTreeMap<String, Entry>> = BTreeMap::new();
+    for table in tables {
+        let ids = match proxy.list(table).await {
+            Ok(ids) => ids,
+            // the table has never been created on this system
+            Err(e) if is_not_found(&e) => Vec::new(),
+            Err(e) => {
+                fail!(e, "failed to list {table}: {e}");
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        let mut entries = BTreeMap::new();
+        for (id, result) in lookup_all(proxy, table, &ids).await {
+            match result {
+                Ok((mut apps, data)) => {
+                    if let Some(permissions) = apps.remove(app) {
+                        let data = with_data.then_some(data);
+                        entries.insert(id, Entry { permissions, data });
+                    }
+                }
+                Err(e) if is_not_found(&e) => {}
+                Err(e) => {
+                    fail!(e, "failed to lookup {id} in {table}: {e}");
+                    code = ExitCode::FAILURE;
+                }
+            }
+        }
+        if all || !entries.is_empty() {
+            report.insert(table, entries);
+        }
+    }
+
+    if format == Format::Json {
+        print_json(&report);
+        return code;
+    }
+
+    for (table, entries) in &report {
+        println!("{table}");
+        if entries.is_empty() {
+            println!("  (none)");
+        }
+        for (id, entry) in entries {
+            println!("  {id}: {}", entry.permissions.join(","));
+            if let Some(data) = &entry.data {
+                println!("    data: {}", &**data);
+            }
+        }
+    }
+    code
+}
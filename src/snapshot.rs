@@ -0,0 +1,314 @@
+//! Offline captures of permission store state and comparisons against them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
+
+use comfy_table::{Cell, Color};
+use serde::{Deserialize, Serialize};
+use zbus::zvariant::OwnedValue;
+
+use crate::filter::IdFilter;
+use crate::store::Store;
+use crate::{LookupResponse, data, is_not_found, lookup_all, new_table};
+
+fn load<T: for<'de> Deserialize<'de>>(path: &str) -> Result<T, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    serde_json::from_str(&content).map_err(|e| format!("{path}: {e}"))
+}
+
+/// The state of a single resource, as printed by `lookup --format json`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ResourceSnapshot {
+    pub permissions: BTreeMap<String, Vec<String>>,
+    /// Associated data, not compared when missing from the snapshot.
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+}
+
+impl ResourceSnapshot {
+    pub fn load(path: &str) -> Result<Self, String> {
+        load(path)
+    }
+
+    pub fn from_lookup(permissions: HashMap<String, Vec<String>>, data: &OwnedValue) -> Self {
+        Self {
+            permissions: permissions.into_iter().collect(),
+            data: serde_json::to_value(data).ok(),
+        }
+    }
+
+    /// The entries and data to write back with `Set`. Snapshots taken
+    /// without data store nothing, like the store does.
+    pub fn to_lookup(&self) -> Result<LookupResponse, String> {
+        let data = match self.data.clone() {
+            Some(data) => data::from_json(data)?,
+            None => OwnedValue::from(0u8),
+        };
+        let permissions = self.permissions.clone().into_iter().collect();
+        Ok((permissions, data))
+    }
+}
+
+/// The state of a whole table at a point in time.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TableSnapshot {
+    pub table: String,
+    pub timestamp: String,
+    pub resources: BTreeMap<String, ResourceSnapshot>,
+}
+
+impl TableSnapshot {
+    pub fn load(path: &str) -> Result<Self, String> {
+        load(path)
+    }
+
+    /// Capture every resource of `table` that `filter` keeps.
+    #[tracing::instrument(skip(proxy))]
+    pub async fn capture(proxy: &Store<'_>, table: &str, filter: &IdFilter) -> zbus::Result<Self> {
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        let mut ids = proxy.list(table).await?;
+        filter.retain(&mut ids);
+
+        let mut resources = BTreeMap::new();
+        for (id, result) in lookup_all(proxy, table, &ids).await {
+            match result {
+                Ok((permissions, data)) => {
+                    resources.insert(id, ResourceSnapshot::from_lookup(permissions, &data));
+                }
+                // the resource was deleted while capturing
+                Err(e) if is_not_found(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(Self {
+            table: table.to_string(),
+            timestamp,
+            resources,
+        })
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Change {
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+/// Differences between two states of a resource.
+#[derive(Serialize, Debug, Default)]
+pub struct ResourceDiff {
+    pub added: BTreeMap<String, Vec<String>>,
+    pub removed: BTreeMap<String, Vec<String>>,
+    pub changed: BTreeMap<String, Change>,
+    pub data_changed: bool,
+}
+
+impl ResourceDiff {
+    pub fn new(before: &ResourceSnapshot, after: &ResourceSnapshot) -> Self {
+        let mut diff = Self::default();
+        for (app, permissions) in &after.permissions {
+            match before.permissions.get(app) {
+                None => {
+                    diff.added.insert(app.clone(), permissions.clone());
+                }
+                Some(previous) if previous != permissions => {
+                    diff.changed.insert(
+                        app.clone(),
+                        Change {
+                            before: previous.clone(),
+                            after: permissions.clone(),
+                        },
+                    );
+                }
+                Some(_) => {}
+            }
+        }
+
+        for (app, permissions) in &before.permissions {
+            if !after.permissions.contains_key(app) {
+                diff.removed.insert(app.clone(), permissions.clone());
+            }
+        }
+
+        if let (Some(before), Some(after)) = (&before.data, &after.data) {
+            diff.data_changed = before != after;
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && !self.data_changed
+    }
+
+    fn rows(&self) -> Vec<[String; 4]> {
+        let mut rows = Vec::new();
+        for (app, after) in &self.added {
+            rows.push(["added".into(), app.clone(), String::new(), after.join(",")]);
+        }
+        for (app, before) in &self.removed {
+            rows.push([
+                "removed".into(),
+                app.clone(),
+                before.join(","),
+                String::new(),
+            ]);
+        }
+        for (app, change) in &self.changed {
+            rows.push([
+                "changed".into(),
+                app.clone(),
+                change.before.join(","),
+                change.after.join(","),
+            ]);
+        }
+        if self.data_changed {
+            rows.push([
+                "data changed".into(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+        rows
+    }
+
+    pub fn print(&self) {
+        let mut table = new_table();
+        table.set_header(vec!["Change", "AppID", "Snapshot", "Live"]);
+        for [change, app, before, after] in self.rows() {
+            table.add_row(vec![
+                change_cell(change),
+                app.into(),
+                before.into(),
+                after.into(),
+            ]);
+        }
+
+        println!("{table}");
+    }
+}
+
+/// Red for what went away and green for what is new, like `diff` does.
+fn change_cell(change: String) -> Cell {
+    let color = match change.as_str() {
+        "added" => Color::Green,
+        "removed" => Color::Red,
+        _ => Color::Yellow,
+    };
+    Cell::new(change).fg(color)
+}
+
+/// Differences between two snapshots of a table, keyed by resource ID.
+#[derive(Serialize, Debug, Default)]
+pub struct TableDiff {
+    pub resources: BTreeMap<String, ResourceDiff>,
+}
+
+impl TableDiff {
+    pub fn new(before: &TableSnapshot, after: &TableSnapshot) -> Self {
+        let empty = ResourceSnapshot::default();
+        let mut diff = Self::default();
+
+        let ids = before.resources.keys().chain(after.resources.keys());
+        for id in ids {
+            if diff.resources.contains_key(id) {
+                continue;
+            }
+
+            let resource = ResourceDiff::new(
+                before.resources.get(id).unwrap_or(&empty),
+                after.resources.get(id).unwrap_or(&empty),
+            );
+            if !resource.is_empty() {
+                diff.resources.insert(id.clone(), resource);
+            }
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    pub fn print(&self) {
+        let mut table = new_table();
+        table.set_header(vec!["Resource ID", "Change", "AppID", "Before", "After"]);
+        for (id, resource) in &self.resources {
+            for [change, app, before, after] in resource.rows() {
+                table.add_row(vec![
+                    Cell::new(id),
+                    change_cell(change),
+                    app.into(),
+                    before.into(),
+                    after.into(),
+                ]);
+            }
+        }
+
+        println!("{table}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::Value;
+
+    use super::*;
+
+    #[test]
+    fn save_and_restore_round_trip() {
+        let location = HashMap::from([("accuracy", Value::from(2u32))]);
+        let data = HashMap::from([
+            ("location", Value::Value(Box::new(location.into()))),
+            ("timestamps", Value::from(vec![1u64, 2])),
+        ]);
+        let data = OwnedValue::try_from(Value::from(data)).unwrap();
+        let entries = HashMap::from([
+            (
+                "org.a.A".to_string(),
+                vec!["EXACT".to_string(), "0".to_string()],
+            ),
+            ("org.b.B".to_string(), Vec::new()),
+        ]);
+        let snapshot = TableSnapshot {
+            table: "location".to_string(),
+            timestamp: "2026-10-14T00:00:00Z".to_string(),
+            resources: BTreeMap::from([
+                (
+                    "location".to_string(),
+                    ResourceSnapshot::from_lookup(entries.clone(), &data),
+                ),
+                (
+                    "empty".to_string(),
+                    ResourceSnapshot::from_lookup(HashMap::new(), &OwnedValue::from(0u8)),
+                ),
+            ]),
+        };
+
+        let saved = serde_json::to_string_pretty(&snapshot).unwrap();
+        let loaded: TableSnapshot = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.table, "location");
+        assert_eq!(
+            loaded.resources["location"].to_lookup().unwrap(),
+            (entries, data)
+        );
+        let (permissions, data) = loaded.resources["empty"].to_lookup().unwrap();
+        assert!(permissions.is_empty());
+        assert_eq!(data, OwnedValue::from(0u8));
+        assert!(TableDiff::new(&snapshot, &loaded).is_empty());
+    }
+
+    #[test]
+    fn snapshots_without_data_restore_no_data() {
+        let resource: ResourceSnapshot =
+            serde_json::from_str(r#"{"permissions": {"org.a.A": ["yes"]}}"#).unwrap();
+        let (permissions, data) = resource.to_lookup().unwrap();
+        assert_eq!(permissions["org.a.A"], ["yes"]);
+        assert_eq!(data, OwnedValue::from(0u8));
+    }
+}
@@ -0,0 +1,192 @@
+//! Summary statistics over the contents of tables.
+
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::store::Store;
+use crate::{Format, is_not_found, lookup_all, new_table, print_json};
+
+/// How often the values most portals store appear, everything else is other.
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+struct ValueCounts {
+    yes: usize,
+    no: usize,
+    ask: usize,
+    other: usize,
+}
+
+impl ValueCounts {
+    fn add(&mut self, value: &str) {
+        match value {
+            "yes" => self.yes += 1,
+            "no" => self.no += 1,
+            "ask" => self.ask += 1,
+            _ => self.other += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &ValueCounts) {
+        self.yes += other.yes;
+        self.no += other.no;
+        self.ask += other.ask;
+        self.other += other.other;
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct LargeResource {
+    table: String,
+    id: String,
+    apps: usize,
+}
+
+#[derive(Serialize, Debug, Default)]
+struct TableStats {
+    table: String,
+    resources: usize,
+    apps: usize,
+    entries: usize,
+    permissions: usize,
+    values: ValueCounts,
+    /// The resources with the most applications, most first
+    largest: Vec<LargeResource>,
+    #[serde(skip)]
+    app_ids: HashSet<String>,
+}
+
+#[tracing::instrument(skip(proxy))]
+async fn table_stats(proxy: &Store<'_>, table: &str, top: usize) -> zbus::Result<TableStats> {
+    let ids = proxy.list(table).await?;
+
+    let mut stats = TableStats {
+        table: table.to_string(),
+        resources: ids.len(),
+        ..TableStats::default()
+    };
+    let mut sizes = Vec::with_capacity(ids.len());
+    for (id, result) in lookup_all(proxy, table, &ids).await {
+        let permissions = match result {
+            Ok((permissions, _)) => permissions,
+            Err(e) if is_not_found(&e) => continue,
+            Err(e) => return Err(e),
+        };
+
+        stats.entries += permissions.len();
+        sizes.push((id, permissions.len()));
+        for (app, permissions) in permissions {
+            stats.permissions += permissions.len();
+            for value in &permissions {
+                stats.values.add(value);
+            }
+            stats.app_ids.insert(app);
+        }
+    }
+    stats.apps = stats.app_ids.len();
+    stats.largest = largest(
+        sizes.into_iter().map(|(id, apps)| LargeResource {
+            table: table.to_string(),
+            id,
+            apps,
+        }),
+        top,
+    );
+
+    Ok(stats)
+}
+
+/// The `top` resources with the most applications, ties broken by ID.
+fn largest(resources: impl Iterator<Item = LargeResource>, top: usize) -> Vec<LargeResource> {
+    let mut resources: Vec<_> = resources.filter(|r| r.apps > 0).collect();
+    resources.sort_by(|a, b| (&a.table, &a.id).cmp(&(&b.table, &b.id)));
+    resources.sort_by_key(|r| Reverse(r.apps));
+    resources.truncate(top);
+    resources
+}
+
+/// Summarize `tables`. Missing ones are skipped with `skip_missing`, for the
+/// well-known tables scanned by default, and reported otherwise.
+pub async fn stats(
+    proxy: &Store<'_>,
+    tables: &[String],
+    skip_missing: bool,
+    top: usize,
+    format: Format,
+) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    let mut rows = Vec::new();
+    for table in tables {
+        match table_stats(proxy, table, top).await {
+            Ok(stats) => rows.push(stats),
+            // the table has never been created on this system
+            Err(e) if skip_missing && is_not_found(&e) => {}
+            Err(e) => {
+                fail!(e, "failed to scan {table}: {e}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if format == Format::Json {
+        print_json(&rows);
+        return code;
+    }
+
+    let mut table = new_table();
+    table.set_header(vec![
+        "Table",
+        "Resources",
+        "Apps",
+        "Entries",
+        "Permissions",
+        "yes",
+        "no",
+        "ask",
+        "other",
+    ]);
+    let mut add_row = |row: &TableStats| {
+        table.add_row(vec![
+            row.table.clone(),
+            row.resources.to_string(),
+            row.apps.to_string(),
+            row.entries.to_string(),
+            row.permissions.to_string(),
+            row.values.yes.to_string(),
+            row.values.no.to_string(),
+            row.values.ask.to_string(),
+            row.values.other.to_string(),
+        ]);
+    };
+    for row in &rows {
+        add_row(row);
+    }
+    if rows.len() > 1 {
+        let mut total = TableStats {
+            table: "total".to_string(),
+            ..TableStats::default()
+        };
+        for row in &rows {
+            total.resources += row.resources;
+            total.entries += row.entries;
+            total.permissions += row.permissions;
+            total.values.merge(&row.values);
+            total.app_ids.extend(row.app_ids.iter().cloned());
+        }
+        total.apps = total.app_ids.len();
+        add_row(&total);
+    }
+    println!("{table}");
+
+    let largest = largest(rows.iter().flat_map(|row| row.largest.clone()), top);
+    if !largest.is_empty() {
+        let mut table = new_table();
+        table.set_header(vec!["Table", "Resource ID", "Apps"]);
+        for resource in largest {
+            table.add_row(vec![resource.table, resource.id, resource.apps.to_string()]);
+        }
+        println!("{table}");
+    }
+    code
+}
@@ -0,0 +1,242 @@
+//! The permission store proxy as used by the subcommands.
+//!
+//! Every call goes through the methods below so it can be traced and timed,
+//! and writes are also recorded in the audit log. Anything else reaches the proxy
+//! through `Deref`.
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use tracing::instrument;
+use zbus::zvariant::Value;
+
+use crate::audit::{self, AuditLog};
+use crate::{LookupResponse, PermissionStoreProxy, TIMING, data_is_unset};
+
+/// Await `call` and, with `--timing`, print how long `method` took along
+/// with its arguments.
+async fn timed<T>(
+    method: &str,
+    args: impl FnOnce() -> String,
+    call: impl Future<Output = zbus::Result<T>>,
+) -> zbus::Result<T> {
+    if !TIMING.load(Ordering::Relaxed) {
+        return call.await;
+    }
+    let start = Instant::now();
+    let result = call.await;
+    let outcome = if result.is_ok() { "ok" } else { "error" };
+    eprintln!(
+        "{method}({}) took {:.1?}, {outcome}",
+        args(),
+        start.elapsed()
+    );
+    result
+}
+
+pub(crate) struct Store<'a> {
+    proxy: &'a PermissionStoreProxy<'a>,
+    audit: Option<AuditLog>,
+    command: &'a str,
+}
+
+impl<'a> Store<'a> {
+    pub fn new(
+        proxy: &'a PermissionStoreProxy<'a>,
+        audit: Option<AuditLog>,
+        command: &'a str,
+    ) -> Self {
+        Self {
+            proxy,
+            audit,
+            command,
+        }
+    }
+
+    fn record(
+        &self,
+        operation: audit::Operation<'_>,
+        result: zbus::Result<()>,
+    ) -> zbus::Result<()> {
+        if let Some(audit) = &self.audit {
+            audit.record(self.command, &operation, &result);
+        }
+        result
+    }
+
+    #[instrument(skip(self), ret(level = "debug"), err(level = "debug"))]
+    pub async fn lookup(&self, table: &str, id: &str) -> zbus::Result<LookupResponse> {
+        timed(
+            "Lookup",
+            || format!("{table:?}, {id:?}"),
+            self.proxy.lookup(table, id),
+        )
+        .await
+    }
+
+    #[instrument(skip(self), ret(level = "debug"), err(level = "debug"))]
+    pub async fn list(&self, table: &str) -> zbus::Result<Vec<String>> {
+        timed("List", || format!("{table:?}"), self.proxy.list(table)).await
+    }
+
+    #[instrument(skip(self), ret(level = "debug"), err(level = "debug"))]
+    pub async fn get_permission(
+        &self,
+        table: &str,
+        id: &str,
+        app: &str,
+    ) -> zbus::Result<Vec<String>> {
+        timed(
+            "GetPermission",
+            || format!("{table:?}, {id:?}, {app:?}"),
+            self.proxy.get_permission(table, id, app),
+        )
+        .await
+    }
+
+    #[instrument(skip(self), ret(level = "debug"), err(level = "debug"))]
+    pub async fn delete(&self, table: &str, id: &str) -> zbus::Result<()> {
+        let result = timed(
+            "Delete",
+            || format!("{table:?}, {id:?}"),
+            self.proxy.delete(table, id),
+        )
+        .await;
+        self.record(
+            audit::Operation {
+                operation: "delete",
+                table,
+                id,
+                app: None,
+                permissions: None,
+                apps: None,
+                data: None,
+            },
+            result,
+        )
+    }
+
+    #[instrument(skip(self), ret(level = "debug"), err(level = "debug"))]
+    pub async fn delete_permission(&self, table: &str, id: &str, app: &str) -> zbus::Result<()> {
+        let result = timed(
+            "DeletePermission",
+            || format!("{table:?}, {id:?}, {app:?}"),
+            self.proxy.delete_permission(table, id, app),
+        )
+        .await;
+        self.record(
+            audit::Operation {
+                operation: "delete_permission",
+                table,
+                id,
+                app: Some(app),
+                permissions: None,
+                apps: None,
+                data: None,
+            },
+            result,
+        )
+    }
+
+    #[instrument(skip(self), ret(level = "debug"), err(level = "debug"))]
+    pub async fn set(
+        &self,
+        table: &str,
+        create: bool,
+        id: &str,
+        app_permissions: &HashMap<String, Vec<String>>,
+        data: &Value<'_>,
+    ) -> zbus::Result<()> {
+        let result = timed(
+            "Set",
+            || {
+                format!(
+                    "{table:?}, {create}, {id:?}, {} app(s)",
+                    app_permissions.len()
+                )
+            },
+            self.proxy.set(table, create, id, app_permissions, data),
+        )
+        .await;
+        self.record(
+            audit::Operation {
+                operation: "set",
+                table,
+                id,
+                app: None,
+                permissions: None,
+                apps: Some(app_permissions),
+                data: (!data_is_unset(data)).then_some(data),
+            },
+            result,
+        )
+    }
+
+    #[instrument(skip(self), ret(level = "debug"), err(level = "debug"))]
+    pub async fn set_permission(
+        &self,
+        table: &str,
+        create: bool,
+        id: &str,
+        app: &str,
+        permissions: &[String],
+    ) -> zbus::Result<()> {
+        let result = timed(
+            "SetPermission",
+            || format!("{table:?}, {create}, {id:?}, {app:?}, {permissions:?}"),
+            self.proxy
+                .set_permission(table, create, id, app, permissions),
+        )
+        .await;
+        self.record(
+            audit::Operation {
+                operation: "set_permission",
+                table,
+                id,
+                app: Some(app),
+                permissions: Some(permissions),
+                apps: None,
+                data: None,
+            },
+            result,
+        )
+    }
+
+    #[instrument(skip(self), ret(level = "debug"), err(level = "debug"))]
+    pub async fn set_value(
+        &self,
+        table: &str,
+        create: bool,
+        id: &str,
+        data: &Value<'_>,
+    ) -> zbus::Result<()> {
+        let result = timed(
+            "SetValue",
+            || format!("{table:?}, {create}, {id:?}, {data}"),
+            self.proxy.set_value(table, create, id, data),
+        )
+        .await;
+        self.record(
+            audit::Operation {
+                operation: "set_value",
+                table,
+                id,
+                app: None,
+                permissions: None,
+                apps: None,
+                data: Some(data),
+            },
+            result,
+        )
+    }
+}
+
+impl<'a> Deref for Store<'a> {
+    type Target = PermissionStoreProxy<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.proxy
+    }
+}
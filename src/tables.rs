@@ -0,0 +1,589 @@
+//! Tables used by the xdg-desktop-portal frontends.
+//!
+//! The permission store has no way to enumerate its tables, so commands that
+//! scan "every table" fall back to this list.
+
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::store::Store;
+use crate::{Format, is_not_found, new_table, print_json};
+
+/// A permission value a portal stores, and what it means there.
+#[derive(Serialize, Debug)]
+pub struct KnownValue {
+    pub value: &'static str,
+    pub meaning: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct KnownTable {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The portal interface that owns the table
+    pub portal: &'static str,
+    /// The fixed resource IDs, empty where the portal makes them up
+    pub resources: &'static [&'static str],
+    pub values: &'static [KnownValue],
+    /// Whether the portal understands nothing but `values`
+    pub closed: bool,
+    /// What the portal keeps in the associated data
+    pub data: &'static str,
+}
+
+pub const KNOWN_TABLES: &[KnownTable] = &[
+    KnownTable {
+        name: "background",
+        description: "Applications allowed to run in the background",
+        portal: "org.freedesktop.portal.Background",
+        resources: &["background"],
+        values: &[
+            KnownValue {
+                value: "yes",
+                meaning: "The application may run in the background",
+            },
+            KnownValue {
+                value: "no",
+                meaning: "The application is stopped when it runs in the background",
+            },
+            KnownValue {
+                value: "ask",
+                meaning: "The portal asks the user every time",
+            },
+        ],
+        closed: true,
+        data: "none",
+    },
+    KnownTable {
+        name: "devices",
+        description: "Access to the camera, microphone and speakers",
+        portal: "org.freedesktop.portal.Device",
+        resources: &["camera", "microphone", "speakers"],
+        values: &[
+            KnownValue {
+                value: "yes",
+                meaning: "The application may use the device",
+            },
+            KnownValue {
+                value: "no",
+                meaning: "The application may not use the device",
+            },
+            KnownValue {
+                value: "ask",
+                meaning: "The portal asks the user every time",
+            },
+        ],
+        closed: true,
+        data: "none",
+    },
+    KnownTable {
+        name: "documents",
+        description: "Files shared with applications through the document portal",
+        portal: "org.freedesktop.portal.Documents",
+        resources: &[],
+        values: &[
+            KnownValue {
+                value: "read",
+                meaning: "The application may read the file",
+            },
+            KnownValue {
+                value: "write",
+                meaning: "The application may write the file",
+            },
+            KnownValue {
+                value: "grant-permissions",
+                meaning: "The application may share the file with other applications",
+            },
+            KnownValue {
+                value: "delete",
+                meaning: "The application may delete the document",
+            },
+        ],
+        closed: true,
+        data: "The file the document stands for",
+    },
+    KnownTable {
+        name: "gamemode",
+        description: "Applications allowed to enable GameMode",
+        portal: "org.freedesktop.portal.GameMode",
+        resources: &["gamemode"],
+        values: &[
+            KnownValue {
+                value: "yes",
+                meaning: "The application may enable GameMode",
+            },
+            KnownValue {
+                value: "no",
+                meaning: "The application may not enable GameMode",
+            },
+        ],
+        closed: true,
+        data: "none",
+    },
+    KnownTable {
+        name: "inhibit",
+        description: "Applications allowed to inhibit logout, user switching, suspend or idle",
+        portal: "org.freedesktop.portal.Inhibit",
+        resources: &["inhibit"],
+        values: &[
+            KnownValue {
+                value: "logout",
+                meaning: "The application may inhibit logging out",
+            },
+            KnownValue {
+                value: "switch",
+                meaning: "The application may inhibit switching users",
+            },
+            KnownValue {
+                value: "suspend",
+                meaning: "The application may inhibit suspending",
+            },
+            KnownValue {
+                value: "idle",
+                meaning: "The application may inhibit marking the session idle",
+            },
+        ],
+        closed: true,
+        data: "none",
+    },
+    KnownTable {
+        name: "location",
+        description: "Location access and the accuracy granted",
+        portal: "org.freedesktop.portal.Location",
+        resources: &["location"],
+        values: &[
+            KnownValue {
+                value: "NONE",
+                meaning: "First value: the application may not access the location",
+            },
+            KnownValue {
+                value: "COUNTRY",
+                meaning: "First value: the location is given down to the country",
+            },
+            KnownValue {
+                value: "CITY",
+                meaning: "First value: the location is given down to the city",
+            },
+            KnownValue {
+                value: "NEIGHBORHOOD",
+                meaning: "First value: the location is given down to the neighborhood",
+            },
+            KnownValue {
+                value: "STREET",
+                meaning: "First value: the location is given down to the street",
+            },
+            KnownValue {
+                value: "EXACT",
+                meaning: "First value: the location is given as precisely as known",
+            },
+            KnownValue {
+                value: "<timestamp>",
+                meaning: "Second value: the last use, in microseconds since the epoch",
+            },
+        ],
+        closed: false,
+        data: "none",
+    },
+    KnownTable {
+        name: "notifications",
+        description: "Applications allowed to send notifications",
+        portal: "org.freedesktop.portal.Notification",
+        resources: &["notification"],
+        values: &[
+            KnownValue {
+                value: "yes",
+                meaning: "The application may send notifications",
+            },
+            KnownValue {
+                value: "no",
+                meaning: "The notifications of the application are dropped",
+            },
+        ],
+        closed: true,
+        data: "none",
+    },
+    KnownTable {
+        name: "realtime",
+        description: "Applications allowed to request realtime scheduling",
+        portal: "org.freedesktop.portal.Realtime",
+        resources: &["realtime"],
+        values: &[
+            KnownValue {
+                value: "yes",
+                meaning: "The application may request realtime scheduling",
+            },
+            KnownValue {
+                value: "no",
+                meaning: "The application may not request realtime scheduling",
+            },
+        ],
+        closed: true,
+        data: "none",
+    },
+    KnownTable {
+        name: "remote-desktop",
+        description: "Remote desktop sessions that may be restored",
+        portal: "org.freedesktop.portal.RemoteDesktop",
+        resources: &[],
+        values: &[KnownValue {
+            value: "yes",
+            meaning: "The session may be restored without asking",
+        }],
+        closed: false,
+        data: "The backend, the version of its data and the session to restore, as (suv)",
+    },
+    KnownTable {
+        name: "screencast",
+        description: "Screencast sessions that may be restored",
+        portal: "org.freedesktop.portal.ScreenCast",
+        resources: &[],
+        values: &[KnownValue {
+            value: "yes",
+            meaning: "The session may be restored without asking",
+        }],
+        closed: false,
+        data: "The backend, the version of its data and the session to restore, as (suv)",
+    },
+    KnownTable {
+        name: "screenshot",
+        description: "Applications allowed to take screenshots without asking",
+        portal: "org.freedesktop.portal.Screenshot",
+        resources: &["screenshot"],
+        values: &[
+            KnownValue {
+                value: "yes",
+                meaning: "The application takes screenshots without the dialog",
+            },
+            KnownValue {
+                value: "no",
+                meaning: "The application may not take screenshots",
+            },
+        ],
+        closed: true,
+        data: "none",
+    },
+    KnownTable {
+        name: "wallpaper",
+        description: "Applications allowed to set the wallpaper without asking",
+        portal: "org.freedesktop.portal.Wallpaper",
+        resources: &["wallpaper"],
+        values: &[
+            KnownValue {
+                value: "yes",
+                meaning: "The application sets the wallpaper without the dialog",
+            },
+            KnownValue {
+                value: "no",
+                meaning: "The application may not set the wallpaper",
+            },
+        ],
+        closed: true,
+        data: "none",
+    },
+];
+
+/// The knowledge about `name`, if it is a well-known table.
+pub fn known(name: &str) -> Option<&'static KnownTable> {
+    KNOWN_TABLES.iter().find(|known| known.name == name)
+}
+
+/// The `permissions` the portal owning `table` would not understand, none
+/// for tables it is not known what to expect in.
+pub fn unknown_values<'a>(table: &str, permissions: &'a [String]) -> Vec<&'a str> {
+    let Some(known) = known(table).filter(|known| known.closed) else {
+        return Vec::new();
+    };
+    permissions
+        .iter()
+        .map(String::as_str)
+        .filter(|permission| !known.values.iter().any(|known| known.value == *permission))
+        .collect()
+}
+
+/// Whether every value the portal owning `table` is known to use is
+/// lowercase, so that values typed in another case can only be mistakes.
+pub fn is_lowercase(table: &str) -> bool {
+    known(table).is_some_and(|known| {
+        !known.values.is_empty()
+            && known
+                .values
+                .iter()
+                .all(|known| known.value == known.value.to_lowercase())
+    })
+}
+
+#[derive(Serialize, Debug)]
+struct TableRow {
+    table: &'static str,
+    description: &'static str,
+    /// Only known with `--probe`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exists: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<usize>,
+}
+
+/// Print the known tables, and with a `proxy` whether they exist here.
+pub async fn tables(proxy: Option<&Store<'_>>, format: Format) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    let mut rows = Vec::with_capacity(KNOWN_TABLES.len());
+    for known in KNOWN_TABLES {
+        let mut row = TableRow {
+            table: known.name,
+            description: known.description,
+            exists: None,
+            resources: None,
+        };
+        if let Some(proxy) = proxy {
+            match proxy.list(known.name).await {
+                Ok(ids) => {
+                    row.exists = Some(true);
+                    row.resources = Some(ids.len());
+                }
+                // the table has never been created on this system
+                Err(e) if is_not_found(&e) => row.exists = Some(false),
+                Err(e) => {
+                    fail!(e, "failed to list {}: {e}", known.name);
+                    code = ExitCode::FAILURE;
+                }
+            }
+        }
+        rows.push(row);
+    }
+
+    if format == Format::Json {
+        print_json(&rows);
+        return code;
+    }
+
+    let mut table = new_table();
+    let mut header = vec!["Table", "Description"];
+    if proxy.is_some() {
+        header.extend(["Exists", "Resources"]);
+    }
+    table.set_header(header);
+    for row in &rows {
+        let mut cells = vec![row.table.to_string(), row.description.to_string()];
+        if proxy.is_some() {
+            let (exists, resources) = match (row.exists, row.resources) {
+                (Some(true), Some(resources)) => ("yes", resources.to_string()),
+                (Some(false), _) => ("no", String::new()),
+                _ => ("error", String::new()),
+            };
+            cells.extend([exists.to_string(), resources]);
+        }
+        table.add_row(cells);
+    }
+
+    println!("{table}");
+    code
+}
+
+/// The number of single character edits turning `a` into `b`, swapping two
+/// neighbors counting as one.
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // distances[i][j] is the distance between a[..i] and b[..j]
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// The value of `table` closest to the unknown `value`, if any is close.
+pub fn suggest(table: &str, value: &str) -> Option<&'static str> {
+    let known = known(table)?;
+    known
+        .values
+        .iter()
+        .map(|known| {
+            (
+                distance(&value.to_lowercase(), &known.value.to_lowercase()),
+                known.value,
+            )
+        })
+        .filter(|(distance, known)| *distance <= known.len().div_ceil(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// The `candidates` close enough to `value` to be what was meant, closest
+/// first and at most three.
+pub fn nearest<'a>(value: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut near: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .map(|candidate| (distance(value, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= candidate.chars().count().div_ceil(3))
+        .collect();
+    near.sort();
+    near.into_iter()
+        .take(3)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Print what is known about `table`, or about every well-known table.
+pub fn explain(table: Option<&str>, format: Format) -> ExitCode {
+    let tables: Vec<&KnownTable> = match table {
+        Some(name) => match known(name) {
+            Some(known) => vec![known],
+            None => {
+                let names: Vec<&str> = KNOWN_TABLES.iter().map(|known| known.name).collect();
+                let e = format!(
+                    "{name} is not a well-known table, those are {}",
+                    names.join(", ")
+                );
+                fail!(e, "{e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => KNOWN_TABLES.iter().collect(),
+    };
+
+    if format == Format::Json {
+        match table {
+            Some(_) => print_json(tables[0]),
+            None => print_json(&tables),
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    for (i, known) in tables.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{} ({})", known.name, known.portal);
+        println!("  {}", known.description);
+        if known.resources.is_empty() {
+            println!("  resources: made up by the portal");
+        } else {
+            println!("  resources: {}", known.resources.join(", "));
+        }
+        println!("  values:");
+        let width = known
+            .values
+            .iter()
+            .map(|value| value.value.len())
+            .max()
+            .unwrap_or_default();
+        for value in known.values {
+            println!("    {:width$}  {}", value.value, value.meaning);
+        }
+        println!("  data: {}", known.data);
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn unknown_values_of_a_known_table() {
+        let permissions = strings(&["yes", "yse", "No"]);
+        assert_eq!(unknown_values("notifications", &permissions), ["yse", "No"]);
+        assert!(unknown_values("notifications", &strings(&["yes", "no"])).is_empty());
+    }
+
+    #[test]
+    fn open_and_unknown_tables_are_not_validated() {
+        let permissions = strings(&["anything"]);
+        assert!(unknown_values("org.example.custom", &permissions).is_empty());
+        let open = KNOWN_TABLES.iter().find(|table| !table.closed).unwrap();
+        assert!(unknown_values(open.name, &permissions).is_empty());
+    }
+
+    #[test]
+    fn distance_counts_a_swap_once() {
+        assert_eq!(distance("yes", "yes"), 0);
+        assert_eq!(distance("yse", "yes"), 1);
+        assert_eq!(distance("ye", "yes"), 1);
+        assert_eq!(distance("", "yes"), 3);
+        assert_eq!(distance("相機", "相机"), 1);
+    }
+
+    #[test]
+    fn suggest_a_known_value() {
+        assert_eq!(suggest("notifications", "yse"), Some("yes"));
+        assert_eq!(suggest("notifications", "YES"), Some("yes"));
+        assert_eq!(suggest("notifications", "yes"), Some("yes"));
+        assert_eq!(suggest("notifications", "banana"), None);
+        assert_eq!(suggest("org.example.custom", "yse"), None);
+    }
+
+    #[test]
+    fn nearest_candidates_closest_first() {
+        let tables = KNOWN_TABLES.iter().map(|table| table.name);
+        assert_eq!(nearest("notifcations", tables.clone()), ["notifications"]);
+        assert_eq!(nearest("devices", tables.clone()).first(), Some(&"devices"));
+        assert!(nearest("wallpapers-and-more", tables.clone()).is_empty());
+        assert_eq!(
+            nearest("abcd", ["abce", "abcd", "abdc", "abed", "xbcd"]),
+            ["abcd", "abce", "abdc"]
+        );
+        // the threshold counts characters, not bytes
+        assert!(nearest("相機", ["照相"]).is_empty());
+    }
+
+    #[test]
+    fn every_known_table_is_complete() {
+        for (i, table) in KNOWN_TABLES.iter().enumerate() {
+            let name = table.name;
+            assert!(!name.is_empty() && name == name.to_lowercase(), "{name}");
+            assert!(
+                KNOWN_TABLES[..i].iter().all(|other| other.name != name),
+                "{name} is listed twice"
+            );
+            assert!(!table.description.is_empty(), "{name} has no description");
+            assert!(
+                table.portal.starts_with("org.freedesktop.portal."),
+                "{name} has no portal"
+            );
+            assert!(!table.data.is_empty(), "{name} does not describe its data");
+            assert!(!table.values.is_empty(), "{name} has no values");
+            for (j, value) in table.values.iter().enumerate() {
+                assert!(!value.value.is_empty(), "{name} has an empty value");
+                assert!(
+                    !value.meaning.is_empty(),
+                    "{name} does not explain {}",
+                    value.value
+                );
+                assert!(
+                    table.values[..j]
+                        .iter()
+                        .all(|other| other.value != value.value),
+                    "{name} lists {} twice",
+                    value.value
+                );
+            }
+            for (j, resource) in table.resources.iter().enumerate() {
+                assert!(!resource.is_empty(), "{name} has an empty resource ID");
+                assert!(
+                    !table.resources[..j].contains(resource),
+                    "{name} lists {resource} twice"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Printing associated data for people: nested dictionaries, arrays and
+//! structures one entry per line, indented below their key.
+
+use std::fmt::Write;
+
+use zbus::zvariant::Value;
+
+/// Indentation of every nesting level.
+const INDENT: &str = "  ";
+
+/// `value` as indented lines, each ending with a line break.
+pub fn pretty(value: &Value<'_>) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, 0);
+    out
+}
+
+/// The value inside any variants wrapped around `value`.
+fn unwrap<'r, 'a>(mut value: &'r Value<'a>) -> &'r Value<'a> {
+    while let Value::Value(inner) = value {
+        value = inner;
+    }
+    value
+}
+
+/// Whether `value` spans several lines, which only containers with
+/// something in them do.
+fn is_block(value: &Value<'_>) -> bool {
+    match unwrap(value) {
+        Value::Array(array) => !array.is_empty(),
+        Value::Dict(dict) => dict.iter().next().is_some(),
+        Value::Structure(structure) => !structure.fields().is_empty(),
+        _ => false,
+    }
+}
+
+/// A value that fits on a line, strings keeping their quotes.
+fn leaf(value: &Value<'_>) -> String {
+    match unwrap(value) {
+        Value::Array(_) => "[]".to_string(),
+        Value::Dict(_) => "{}".to_string(),
+        Value::Structure(_) => "()".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// A dictionary key, without quotes when it is a string.
+fn key(value: &Value<'_>) -> String {
+    match unwrap(value) {
+        Value::Str(key) => key.to_string(),
+        key => leaf(key),
+    }
+}
+
+fn write_value(out: &mut String, value: &Value<'_>, depth: usize) {
+    let pad = INDENT.repeat(depth);
+    let value = unwrap(value);
+    let items: Vec<(String, &Value<'_>)> = match value {
+        Value::Dict(dict) if is_block(value) => dict
+            .iter()
+            .map(|(k, v)| (format!("{}:", key(k)), v))
+            .collect(),
+        Value::Array(array) if is_block(value) => {
+            array.inner().iter().map(|v| ("-".to_string(), v)).collect()
+        }
+        Value::Structure(structure) if is_block(value) => structure
+            .fields()
+            .iter()
+            .map(|v| ("-".to_string(), v))
+            .collect(),
+        value => {
+            let _ = writeln!(out, "{pad}{}", leaf(value));
+            return;
+        }
+    };
+
+    for (label, item) in items {
+        if is_block(item) {
+            let _ = writeln!(out, "{pad}{label}");
+            write_value(out, item, depth + 1);
+        } else {
+            let _ = writeln!(out, "{pad}{label} {}", leaf(item));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use zbus::zvariant::{Array, Dict, Signature, StructureBuilder};
+
+    use super::*;
+
+    #[test]
+    fn array_of_dicts() {
+        let apps = Array::from(vec![
+            Value::from(HashMap::from([("accuracy", Value::from(2u32))])),
+            Value::from(HashMap::from([("name", Value::from("Maps"))])),
+        ]);
+        assert_eq!(
+            pretty(&Value::from(apps)),
+            "-\n  accuracy: uint32 2\n-\n  name: \"Maps\"\n"
+        );
+    }
+
+    #[test]
+    fn dict_of_nested_containers() {
+        let structure = StructureBuilder::new()
+            .add_field(1u8)
+            .add_field("last")
+            .build()
+            .unwrap();
+        let data = HashMap::from([
+            ("a", Value::from(vec![1i32, 2])),
+            ("b", Value::from(structure)),
+        ]);
+        assert_eq!(
+            pretty(&Value::from(data)),
+            "a:\n  - 1\n  - 2\nb:\n  - byte 0x01\n  - \"last\"\n"
+        );
+    }
+
+    #[test]
+    fn variants_are_unwrapped() {
+        let variant = |value: Value<'static>| Value::Value(Box::new(value));
+        assert_eq!(pretty(&variant(variant(Value::from(7i32)))), "7\n");
+
+        let inner = HashMap::from([("n", variant(Value::from(1u8)))]);
+        let outer = variant(Value::from(inner));
+        assert_eq!(pretty(&outer), "n: byte 0x01\n");
+    }
+
+    #[test]
+    fn empty_containers_fit_on_a_line() {
+        let empty_array = Value::from(Array::new(&Signature::Str));
+        let empty_dict = Value::from(Dict::new(&Signature::Str, &Signature::Variant));
+        assert_eq!(pretty(&empty_array), "[]\n");
+        assert_eq!(pretty(&empty_dict), "{}\n");
+
+        let data = HashMap::from([
+            ("apps", Value::from(Array::new(&Signature::Str))),
+            (
+                "options",
+                Value::from(Dict::new(&Signature::Str, &Signature::Variant)),
+            ),
+        ]);
+        assert_eq!(pretty(&Value::from(data)), "apps: []\noptions: {}\n");
+    }
+
+    #[test]
+    fn strings_are_quoted_and_escaped() {
+        assert_eq!(
+            pretty(&Value::from("a \"quoted\"\nline\\")),
+            "\"a \\\"quoted\\\"\\nline\\\\\"\n"
+        );
+        // keys are printed as they are, only values are quoted
+        let data = HashMap::from([("key with: colon", Value::from("tab\there"))]);
+        assert_eq!(
+            pretty(&Value::from(data)),
+            "key with: colon: \"tab\\there\"\n"
+        );
+    }
+}
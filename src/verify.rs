@@ -0,0 +1,90 @@
+//! Reading permissions back after a write, see `--verify`.
+
+use std::time::Duration;
+
+use clap::Args;
+
+use crate::error::ErrorKind;
+use crate::is_not_found;
+use crate::store::Store;
+
+/// Read-back options shared by every command that writes permissions.
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Read the permissions back after writing and fail unless they match
+    #[arg(long, default_value_t = false)]
+    verify: bool,
+
+    /// Read a mismatch back this many more times, for backends that apply
+    /// writes late
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "verify")]
+    verify_retries: u32,
+
+    /// How long to wait before every retry
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "100ms",
+        value_parser = humantime::parse_duration,
+        requires = "verify"
+    )]
+    verify_delay: Duration,
+}
+
+impl VerifyArgs {
+    /// Check that `app` holds exactly `expected` on `id`, `None` meaning no
+    /// entry. `Some(true)` when it does or when disabled, `Some(false)` once
+    /// the mismatch is reported and `None` once the failure is.
+    pub async fn check(
+        &self,
+        proxy: &Store<'_>,
+        table: &str,
+        id: &str,
+        app: &str,
+        expected: Option<&[String]>,
+    ) -> Option<bool> {
+        if !self.verify {
+            return Some(true);
+        }
+
+        let mut attempt = 0;
+        let actual = loop {
+            let actual = match proxy.lookup(table, id).await {
+                Ok((mut apps, _)) => apps.remove(app),
+                Err(e) if is_not_found(&e) => None,
+                Err(e) => {
+                    fail!(e, "{id} {app}: failed to read the permissions back: {e}");
+                    return None;
+                }
+            };
+            if actual.as_deref() == expected || attempt == self.verify_retries {
+                break actual;
+            }
+            attempt += 1;
+            tokio::time::sleep(self.verify_delay).await;
+        };
+
+        if actual.as_deref() == expected {
+            return Some(true);
+        }
+        let show = |permissions: Option<&[String]>| match permissions {
+            Some(permissions) => format!("[{}]", permissions.join(", ")),
+            None => "no entry".to_string(),
+        };
+        let mut message = format!(
+            "{id} {app}: wrote {} but read back {}",
+            show(expected),
+            show(actual.as_deref())
+        );
+        let expected = expected.unwrap_or_default();
+        let actual = actual.as_deref().unwrap_or_default();
+        for permission in expected.iter().filter(|p| !actual.contains(p)) {
+            message.push_str(&format!("\n  - {permission}"));
+        }
+        for permission in actual.iter().filter(|p| !expected.contains(p)) {
+            message.push_str(&format!("\n  + {permission}"));
+        }
+        fail!(ErrorKind::Mismatch, "{message}");
+        Some(false)
+    }
+}
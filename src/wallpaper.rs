@@ -0,0 +1,82 @@
+//! Shortcuts for the wallpaper table, which only has a single resource.
+
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::error::ErrorKind;
+use crate::shortcut::{Resource, check_app};
+use crate::store::Store;
+use crate::{Format, WallpaperAction, print_json};
+
+const RESOURCE: Resource = Resource {
+    table: "wallpaper",
+    id: "wallpaper",
+    what: "wallpaper",
+};
+
+#[derive(Serialize, Debug)]
+struct Status<'a> {
+    app: &'a str,
+    /// `None` when the application has no entry
+    permissions: Option<Vec<String>>,
+}
+
+pub async fn wallpaper(
+    proxy: &Store<'_>,
+    action: WallpaperAction,
+    app: Option<&str>,
+    format: Format,
+) -> ExitCode {
+    if action == WallpaperAction::List {
+        return RESOURCE.list(proxy, "Wallpaper", format).await;
+    }
+    let Some(app) = app else {
+        fail!(ErrorKind::InvalidInput, "expected <APP>");
+        return ExitCode::FAILURE;
+    };
+    if !check_app(app) {
+        return ExitCode::FAILURE;
+    }
+
+    let permission = match action {
+        WallpaperAction::Allow => "yes",
+        WallpaperAction::Deny => "no",
+        WallpaperAction::Status => return print_status(proxy, app, format).await,
+        WallpaperAction::List => unreachable!("handled above"),
+    };
+    if !RESOURCE.set(proxy, app, &[permission.to_string()]).await {
+        return ExitCode::FAILURE;
+    }
+    if permission == "yes" {
+        status!("{app} may now set the wallpaper without asking");
+    } else {
+        status!("{app} may no longer set the wallpaper");
+    }
+    ExitCode::SUCCESS
+}
+
+async fn print_status(proxy: &Store<'_>, app: &str, format: Format) -> ExitCode {
+    let Some(permissions) = RESOURCE.permissions(proxy, app).await else {
+        return ExitCode::FAILURE;
+    };
+
+    if format == Format::Json {
+        print_json(&Status { app, permissions });
+        return ExitCode::SUCCESS;
+    }
+    match permissions.as_deref() {
+        Some([value]) if value == "yes" => {
+            println!("{app} is allowed to set the wallpaper without asking")
+        }
+        Some([value]) if value == "no" => println!("{app} is not allowed to set the wallpaper"),
+        // the portal shows its dialog for anything but yes and no
+        None => println!("{app} has no wallpaper setting, the portal will ask"),
+        Some([value]) if value == "ask" => println!("{app} is asked before setting the wallpaper"),
+        Some(values) => println!(
+            "{app} has the unusual wallpaper setting {}, the portal will ask",
+            values.join(",")
+        ),
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,199 @@
+//! Following the changes other clients make to the permission store, and
+//! reacting to them with `--exec`.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, ExitCode};
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::error::ErrorKind;
+use crate::store::Store;
+use crate::{ChangedStream, Format, interrupt};
+
+/// How long to wait before the first attempt to reconnect, doubled after
+/// every failed one up to `MAX_RECONNECT_DELAY`.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// A `Changed` signal, which carries every entry of the resource.
+#[derive(Serialize, Debug)]
+struct Change<'a> {
+    table: &'a str,
+    id: &'a str,
+    deleted: bool,
+    permissions: BTreeMap<&'a str, &'a [String]>,
+}
+
+/// Print every change to `table`, or to any table, until Ctrl-C, running
+/// `exec` for each. Losing the bus is survived by reconnecting, up to
+/// `max_reconnect` times in a row.
+pub async fn watch(
+    proxy: &Store<'_>,
+    table: Option<&str>,
+    exec: Option<&str>,
+    max_reconnect: u32,
+    format: Format,
+) -> ExitCode {
+    let mut signals = match proxy.receive_changed().await {
+        Ok(signals) => signals,
+        Err(e) => {
+            fail!(e, "failed to watch the permission store: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    status!("Watching for changes, press Ctrl-C to stop");
+
+    loop {
+        let signal = tokio::select! {
+            signal = signals.next() => signal,
+            // dropping the stream unsubscribes
+            () = interrupt::wait() => return ExitCode::from(interrupt::EXIT_CODE),
+        };
+        let Some(signal) = signal else {
+            if max_reconnect == 0 {
+                break;
+            }
+            // the subscription went away with the connection
+            match reconnect(max_reconnect).await {
+                Some(resubscribed) => signals = resubscribed,
+                None if interrupt::interrupted() => {
+                    return ExitCode::from(interrupt::EXIT_CODE);
+                }
+                None => {
+                    fail!(
+                        ErrorKind::Dbus,
+                        "giving up after {max_reconnect} attempts to reconnect"
+                    );
+                    return ExitCode::FAILURE;
+                }
+            }
+            continue;
+        };
+        let args = match signal.args() {
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("warning: ignoring a malformed Changed signal: {e}");
+                continue;
+            }
+        };
+        if table.is_some_and(|table| table != *args.table()) {
+            continue;
+        }
+
+        let change = Change {
+            table: args.table(),
+            id: args.id(),
+            deleted: *args.deleted(),
+            permissions: args
+                .permissions()
+                .iter()
+                .map(|(app, permissions)| (app.as_str(), permissions.as_slice()))
+                .collect(),
+        };
+        print_change(&change, format);
+        if let Some(command) = exec {
+            run_hooks(command, &change);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Connect to the permission store again and subscribe to its changes,
+/// waiting longer after every failed attempt. `None` after `attempts`
+/// failures or Ctrl-C.
+async fn reconnect(attempts: u32) -> Option<ChangedStream> {
+    let mut delay = RECONNECT_DELAY;
+    for attempt in 1..=attempts {
+        eprintln!(
+            "lost the connection to the permission store, reconnecting in {} ({attempt}/{attempts})",
+            humantime::format_duration(delay)
+        );
+        tokio::select! {
+            () = tokio::time::sleep(delay) => {}
+            () = interrupt::wait() => return None,
+        }
+        if let Some(proxy) = crate::connect().await {
+            match proxy.receive_changed().await {
+                Ok(signals) => {
+                    status!("Reconnected, watching for changes again");
+                    return Some(signals);
+                }
+                Err(e) => fail!(e, "failed to watch the permission store: {e}"),
+            }
+        }
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+    None
+}
+
+/// One line per change, JSON Lines with `--format json` so that tools can
+/// read them as they come.
+fn print_change(change: &Change<'_>, format: Format) {
+    if format == Format::Json {
+        match serde_json::to_string(change) {
+            Ok(json) => println!("{json}"),
+            Err(e) => fail!(e, "failed to serialize output: {e}"),
+        }
+        return;
+    }
+
+    // the entries of a deleted resource are the ones it had
+    if change.deleted {
+        println!("{} {}: deleted", change.table, change.id);
+        return;
+    }
+    if change.permissions.is_empty() {
+        println!("{} {}: no entries", change.table, change.id);
+    }
+    for (app, permissions) in &change.permissions {
+        println!(
+            "{} {} {app}: {}",
+            change.table,
+            change.id,
+            permissions.join(",")
+        );
+    }
+}
+
+/// Run `command` through the shell once for every application of `change`,
+/// or once without one when it has none. A failing hook is only reported.
+fn run_hooks(command: &str, change: &Change<'_>) {
+    // hooks writing to the same terminal come after the change they are for
+    let _ = std::io::stdout().flush();
+
+    let apps: Vec<(&str, &[String])> = if change.permissions.is_empty() {
+        vec![("", &[])]
+    } else {
+        change
+            .permissions
+            .iter()
+            .map(|(app, permissions)| (*app, *permissions))
+            .collect()
+    };
+    for (app, permissions) in apps {
+        if interrupt::interrupted() {
+            break;
+        }
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("XDG_PERM_TABLE", change.table)
+            .env("XDG_PERM_ID", change.id)
+            .env("XDG_PERM_APP", app)
+            .env("XDG_PERM_PERMISSIONS", permissions.join(","))
+            .env("XDG_PERM_DELETED", change.deleted.to_string())
+            .status();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!(
+                "warning: the hook for {} {} {app} failed: {status}",
+                change.table, change.id
+            ),
+            Err(e) => eprintln!("warning: failed to run the hook: {e}"),
+        }
+    }
+}